@@ -1,5 +1,66 @@
 use clap::Parser;
 
+/// Order of files among their siblings, in the TUI tree and in the output.
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// By path (files and directories interleaved alphabetically, unless --dirs-first).
+    #[default]
+    Name,
+    /// Largest files first.
+    Size,
+    /// Most recently modified files first.
+    Mtime,
+    /// By extension, then name.
+    Ext,
+}
+
+/// How the file blocks are ordered in the output.
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputOrder {
+    /// Sorted by full path.
+    #[default]
+    Path,
+    /// Same order as the files appear in the rendered tree.
+    Tree,
+}
+
+/// How file paths are written in the output headers.
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathStyle {
+    /// Relative to the scan root (or the current directory with --paths-from-cwd).
+    #[default]
+    Relative,
+    /// Absolute path.
+    Absolute,
+    /// File name only.
+    Name,
+}
+
+/// Color palette of the interactive selector.
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Tuned for dark terminal backgrounds.
+    #[default]
+    Default,
+    /// Tuned for light terminal backgrounds.
+    Light,
+    /// Bright colors and a strongly contrasting highlight.
+    HighContrast,
+}
+
+/// How --manifest lists the selected paths.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManifestFormat {
+    /// One path per line.
+    Lines,
+    /// A JSON array of paths.
+    Json,
+}
+
 fn command_long_about() -> String {
     format!(
         "repoyank v{} - Interactively select and copy code snippets.
@@ -17,15 +78,81 @@ ARGUMENTS:
         If the first PATTERN provided is an existing directory, it is used as the
         scan root. Otherwise, the current working directory is the scan root.
         If no patterns are given, it defaults to selecting all files ('**/*')
-        under the scan root.
+        under the scan root. Combined with --type, this means `repoyank -t rs src`
+        takes every .rs file under src: --type alone decides which files are kept.
 
 OPTIONS (see `repoyank --help` for full details):
     -a, --all                 Skip TUI, yank all files matching patterns & filters.
     -t, --type <EXT[,EXT...]> Filter by file extensions (e.g., rs,md).
+        --exclude-type <EXT[,EXT...]>  Drop files with these extensions.
+        --exclude <GLOB[,...]>  Skip paths matching these globs (adds to built-ins).
+        --no-default-excludes  Don't skip node_modules, target, dist, etc.
     -s, --select <GLOB[,...]> Pre-select TUI items matching these globs.
     -i, --include-ignored     Include files ignored by .gitignore.
+        --include-ignored-globs <GLOB[,...]>  Include just these ignored paths.
     -n, --dry-run             Print selection and tree, but don't copy to clipboard.
     -o, --output <FILE>       Write output to FILE instead of clipboard.
+        --no-tree             Omit the directory tree from the output.
+        --tree-only           Output only the tree of matched files (skips TUI).
+        --tree-tokens         Annotate tree lines with approximate token counts.
+        --compact-tree        Collapse single-child directory chains into one line.
+        --clipboard-max-bytes <N>  Refuse to copy output larger than N bytes.
+        --list-omitted        Append a list of matched files that were not selected.
+        --tree-output <FILE>  Also write just the directory tree to FILE.
+        --group-by-ext        Group file contents by extension in the output.
+        --restore             Pre-select the files from the last confirmed TUI run.
+        --newer-than <DURATION>  Pre-select files modified within e.g. 2h or 1d.
+        --include-binary-base64  Include small binary files base64-encoded.
+        --max-binary-bytes <N>   Size cap for --include-binary-base64.
+        --lossy               Decode non-UTF-8 text files with replacement characters.
+        --clipboard <BACKEND> Clipboard backend: auto, arboard, wl-copy, xclip, pbcopy, osc52.
+        --output-order <ORDER> Order file blocks by path (default) or tree.
+        --primary             Copy to the PRIMARY selection (Linux middle-click paste).
+        --max-file-tokens <N> Skip files estimated above N tokens.
+        --paths-from-cwd      Show output paths relative to the current directory.
+        --sort <KEY>          Sort files by name (default), size, mtime or ext.
+        --dirs-first          List directories before files within each directory.
+        --follow-symlinks     Descend into symlinked directories.
+        --send <PROVIDER>     Also send the output to an LLM API (`send` feature).
+        --prompt <TEXT>       Prompt to send with --send (`send` feature).
+        --depth <N>           Only walk N directory levels below the root.
+        --max-files-per-dir <N>  Include at most N files per directory.
+        --toc                 Start the output with a numbered table of contents.
+        --hidden              Include dotfiles that are not gitignored.
+        --ignore-directives   Ignore repoyank: magic comments in files.
+        --git-modified        Only include files with uncommitted changes.
+        --pin <GLOB[,...]>    Emit files matching these globs first, in glob order.
+        --git-diff <REF>      Only include files changed since the git ref REF.
+        --staged              Only include files staged in the git index.
+        --annotate-git        Add the last commit (sha, date, author) to file headers.
+        --strip-comments      Remove code comments from file contents.
+        --collapse-blank-lines  Collapse runs of blank lines into one.
+        --redact <REGEX>      Replace regex matches in contents with [REDACTED].
+        --redact-secrets      Redact AWS keys, Bearer tokens and private keys.
+        --truncate-lines <N>  Cut content lines longer than N characters.
+        --head <N>            Include only the first N lines of each file.
+        --theme <THEME>       TUI colors: default, light or high-contrast.
+        --count-only          Print per-directory size and token counts, copy nothing.
+        --path-style <STYLE>  Header paths: relative (default), absolute or name.
+        --prefix-tree-root[=LABEL]  Root label of the tree (default: directory name).
+        --icons               Show file-type emoji in the tree and the TUI.
+        --max-files <N>       Abort when more than N files match (default 1000).
+        --min-file-size <N>   Skip empty files and files smaller than N bytes.
+        --include-empty-dirs  Keep directories without yanked files in the tree.
+        --append              Append to the clipboard text instead of replacing it.
+        --no-clipboard-daemon  Copy without the background clipboard daemon on Linux.
+        --summary-footer      End the output with a per-file token and line summary.
+        --manifest[=json]     Print the paths that would be yanked, copy nothing.
+        --max-tokens <N>      Warn when the output exceeds about N tokens.
+        --max-tokens-strict   Fail instead of warning when over --max-tokens.
+        --label-language      Name each file's language in its header.
+        --from-file <PATH>    Select the paths/globs listed in PATH (- for stdin).
+    -q, --quiet               Suppress the tree and success/summary messages.
+        --no-warnings         Suppress warnings about individual files.
+        --template <FILE>     Render the output from a template file.
+        --split <TOKENS>      Split the output into chunks of whole files under TOKENS.
+        --init                Write a starter repoyank.toml here and exit.
+        --force               With --init, overwrite an existing repoyank.toml.
     -h, --help                Show help.
     -V, --version             Show version.
 
@@ -36,13 +163,26 @@ EXAMPLES:
     repoyank -a 'tests/**/*.cpp'      # Instantly yank exactly the test cpp files
     repoyank -a -t rs,md              # Yank all Rust & MD files, no TUI
     repoyank -n -a docs/**/*.md       # See what would be yanked (dry run)
+    repoyank --restore                # Start from the previous selection
+
+CONFIG:
+    Defaults are read from repoyank.toml in the scan root and from
+    $XDG_CONFIG_HOME/repoyank/config.toml; command-line flags take precedence.
+
+EXIT STATUS:
+    0    Output was copied, written or printed.
+    1    An error occurred.
+    2    Nothing matched or nothing was selected.
+    3    The TUI was quit without confirming a selection.
+    4    The output could not be copied to the clipboard.
+    5    The output was over --max-tokens with --max-tokens-strict.
 ",
         env!("CARGO_PKG_VERSION")
     )
 }
 
 /// repoyank – copy annotated source snippets to clipboard
-#[derive(Parser, Debug)]
+#[derive(Parser, Clone, Debug)]
 #[command(author, version, about = "Interactively select and copy code snippets.", long_about = command_long_about())]
 pub struct Cli {
     /// Globs to select files/directories. First dir PATTERN sets scan root.
@@ -64,6 +204,22 @@ pub struct Cli {
     )]
     pub type_filter: Vec<String>,
 
+    /// Drop files with these comma-separated extensions (e.g., lock,snap; no dots).
+    /// Applied after --type, so the two combine as "these types except those".
+    #[arg(long = "exclude-type", value_delimiter = ',', value_name = "EXT")]
+    pub exclude_types: Vec<String>,
+
+    /// Skip paths matching these comma-separated globs, relative to the scan root. A glob
+    /// without '/' matches at any depth (`build` prunes every build directory). Added to the
+    /// built-in excludes and to REPOYANK_EXCLUDE; applies even with --include-ignored.
+    #[arg(long = "exclude", value_delimiter = ',', value_name = "GLOB")]
+    pub exclude_globs: Vec<String>,
+
+    /// Do not prune the built-in excludes (.git, node_modules, target, dist, __pycache__,
+    /// .venv, .tox). Globs from --exclude and REPOYANK_EXCLUDE still apply.
+    #[arg(long)]
+    pub no_default_excludes: bool,
+
     /// Pre-select TUI items matching these comma-separated globs.
     /// Globs are relative to the scan root.
     #[arg(
@@ -79,6 +235,13 @@ pub struct Cli {
     #[arg(short = 'i', long)]
     pub include_ignored: bool,
 
+    /// Include ignored files matching these comma-separated globs, leaving every other
+    /// ignore rule in place. Globs use .gitignore syntax relative to the scan root: one with
+    /// a '/' is anchored there (`dist/bundle.js`), one without matches at any depth
+    /// (`*.min.js`). --exclude still applies.
+    #[arg(long, value_delimiter = ',', value_name = "GLOB")]
+    pub include_ignored_globs: Vec<String>,
+
     /// Print selection and tree, but don't copy to clipboard.
     #[arg(short = 'n', long)]
     pub dry_run: bool,
@@ -86,4 +249,334 @@ pub struct Cli {
     /// Write output to file instead of clipboard.
     #[arg(short = 'o', long = "output", value_name = "FILE")]
     pub output_file: Option<std::path::PathBuf>,
+
+    /// Omit the directory tree header; emit only the file blocks.
+    #[arg(long)]
+    pub no_tree: bool,
+
+    /// Output only the directory tree of all matched files, without file contents.
+    /// Skips the TUI, since every candidate is included.
+    #[arg(long, conflicts_with = "no_tree")]
+    pub tree_only: bool,
+
+    /// Annotate each file in the output tree with its approximate token count.
+    /// Directory lines show the total of their descendants.
+    #[arg(long)]
+    pub tree_tokens: bool,
+
+    /// Collapse chains of directories that each hold a single directory into one tree line,
+    /// e.g. `src/main/java/com/example/`. Affects the output tree, not the TUI.
+    #[arg(long)]
+    pub compact_tree: bool,
+
+    /// Refuse to copy output larger than this many bytes to the clipboard (0 = no limit).
+    #[arg(long, value_name = "N", default_value_t = 4 * 1024 * 1024)]
+    pub clipboard_max_bytes: u64,
+
+    /// Append a section listing files that matched the patterns and filters
+    /// but were not selected. Counts towards the token total.
+    #[arg(long, conflicts_with = "template")]
+    pub list_omitted: bool,
+
+    /// Also write just the directory tree to this file, alongside the normal output.
+    #[arg(long, value_name = "FILE")]
+    pub tree_output: Option<std::path::PathBuf>,
+
+    /// Group the file blocks in the output by extension, each group under a
+    /// `=== <Language> files ===` heading. The tree is unchanged.
+    #[arg(long, conflicts_with = "template")]
+    pub group_by_ext: bool,
+
+    /// Pre-select the files yanked in the last confirmed TUI run from this scan root, as
+    /// saved in $XDG_STATE_HOME/repoyank/last_selections.json. Dry runs are not saved.
+    #[arg(long)]
+    pub restore: bool,
+
+    /// Pre-select files in the TUI modified within this long, e.g. 30m, 2h, 3d or 1w.
+    /// Adds to --select and --restore.
+    #[arg(long, value_name = "DURATION", value_parser = crate::utils::parse_duration)]
+    pub newer_than: Option<std::time::Duration>,
+
+    /// Include binary files base64-encoded instead of as unreadable text.
+    /// Files above --max-binary-bytes get a placeholder.
+    #[arg(long)]
+    pub include_binary_base64: bool,
+
+    /// Largest binary file (in bytes) included by --include-binary-base64.
+    #[arg(long, value_name = "N", default_value_t = 64 * 1024)]
+    pub max_binary_bytes: u64,
+
+    /// Include text files that are not valid UTF-8 (e.g. Latin-1) with the invalid bytes
+    /// replaced by U+FFFD, and a warning, instead of an error block. Binary files are still
+    /// left unread.
+    #[arg(long)]
+    pub lossy: bool,
+
+    /// Clipboard backend. `wl-copy`, `xclip` and `pbcopy` shell out to those tools; `osc52`
+    /// copies through the terminal emulator via an escape sequence, which works over SSH.
+    #[arg(long, value_enum, value_name = "BACKEND", default_value_t = crate::clipboard::ClipboardBackend::Auto)]
+    pub clipboard: crate::clipboard::ClipboardBackend,
+
+    /// Order of the file blocks in the output. `tree` follows the rendered
+    /// directory tree exactly, top to bottom.
+    #[arg(long, value_enum, value_name = "ORDER", default_value_t = OutputOrder::Path)]
+    pub output_order: OutputOrder,
+
+    /// Copy to the PRIMARY selection (middle-click paste) instead of the regular
+    /// clipboard. Linux only; ignored with a warning elsewhere.
+    #[arg(long)]
+    pub primary: bool,
+
+    /// Skip individual files whose estimated token count is above this limit.
+    /// Files are then read once, before the output starts, and held until it is written.
+    #[arg(long, value_name = "N")]
+    pub max_file_tokens: Option<usize>,
+
+    /// Show file headers and the tree root relative to the current directory instead
+    /// of the scan root. Files outside the current directory stay root-relative.
+    #[arg(long)]
+    pub paths_from_cwd: bool,
+
+    /// Order of files within each directory. For everything but `name`, subdirectories
+    /// come first (by name) and the file blocks in the output follow the tree.
+    #[arg(long, value_enum, value_name = "KEY", default_value_t = SortOrder::Name)]
+    pub sort: SortOrder,
+
+    /// List subdirectories before files within each directory, in the TUI and the output.
+    /// Only changes `--sort name`, since the other orders already do this.
+    #[arg(long, alias = "sort-dirs-first")]
+    pub dirs_first: bool,
+
+    /// Descend into symlinked directories. Symlink cycles are detected and skipped.
+    /// Links can point outside the repository, pulling in files from elsewhere.
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// After copying, send the output and --prompt to an LLM API and print the streamed
+    /// reply. Makes a network request; the API key is read from the environment. Only in
+    /// builds with the `send` feature.
+    #[cfg(feature = "send")]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "PROVIDER",
+        requires = "prompt",
+        conflicts_with_all = ["count_only", "manifest", "split"]
+    )]
+    pub send: Option<crate::send::Provider>,
+
+    /// Prompt sent ahead of the output with --send.
+    #[cfg(feature = "send")]
+    #[arg(long, value_name = "TEXT", requires = "send")]
+    pub prompt: Option<String>,
+
+    /// Limit how deep the directory walk goes (1 = only the root's immediate children).
+    /// The walk is cut off before glob patterns are applied, so deeper matches are never seen.
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+
+    /// Include at most N files from any single directory, keeping the first N in --sort
+    /// order. Files left out are listed on stderr.
+    #[arg(long, value_name = "N")]
+    pub max_files_per_dir: Option<usize>,
+
+    /// Start the output with a numbered table of contents listing every included file
+    /// with its approximate token and line count.
+    #[arg(long, conflicts_with = "template")]
+    pub toc: bool,
+
+    /// Include hidden files and directories (dotfiles). Unlike --include-ignored,
+    /// .gitignore rules still apply.
+    #[arg(long)]
+    pub hidden: bool,
+
+    /// Ignore `repoyank:` magic comments (skip, max-lines, head, tail) in files.
+    #[arg(long)]
+    pub ignore_directives: bool,
+
+    /// Only consider files with uncommitted changes (modified, added, renamed or
+    /// untracked), as reported by `git status`. Combines with PATTERNs and --type.
+    #[arg(long)]
+    pub git_modified: bool,
+
+    /// Emit files matching these comma-separated globs first, in the order of the globs.
+    /// The tree is unchanged; pins matching no yanked file are ignored.
+    #[arg(long = "pin", value_delimiter = ',', value_name = "GLOB")]
+    pub pin_globs: Vec<String>,
+
+    /// Only consider files that differ between REF and the working tree
+    /// (`git diff --name-only REF`). Combines with PATTERNs and --type.
+    #[arg(long, value_name = "REF", conflicts_with = "git_modified")]
+    pub git_diff: Option<String>,
+
+    /// Only consider files staged in the git index (`git diff --cached --name-only`).
+    /// Their working-tree contents are yanked. Combines with PATTERNs and --type.
+    #[arg(long, conflicts_with_all = ["git_modified", "git_diff"])]
+    pub staged: bool,
+
+    /// Add a `Last modified: <sha> <date> by <author>` line to each file header, from
+    /// `git log -1`. Runs git once per file; ignored outside a git repository.
+    #[arg(long, conflicts_with = "template")]
+    pub annotate_git: bool,
+
+    /// Remove comments from file contents to save tokens. Language-aware by extension;
+    /// string literals are left intact and line numbers are preserved.
+    #[arg(long)]
+    pub strip_comments: bool,
+
+    /// Collapse runs of blank lines in file contents into a single blank line.
+    #[arg(long)]
+    pub collapse_blank_lines: bool,
+
+    /// Replace matches of this regex in file contents with `[REDACTED]`. Repeatable.
+    #[arg(long, value_name = "REGEX")]
+    pub redact: Vec<String>,
+
+    /// Redact common secrets: AWS access keys, `Bearer` tokens and private key blocks.
+    #[arg(long)]
+    pub redact_secrets: bool,
+
+    /// Cut content lines longer than N characters, noting how many were dropped.
+    /// The dropped text does not count towards token estimates (0 = no limit).
+    #[arg(long, value_name = "N")]
+    pub truncate_lines: Option<usize>,
+
+    /// Include only the first N lines of each file, followed by `... [M more lines]`
+    /// (0 = no limit).
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub head: usize,
+
+    /// Color palette of the interactive selector: default, light or high-contrast.
+    #[arg(long, value_enum, value_name = "THEME", default_value_t = Theme::Default)]
+    pub theme: Theme,
+
+    /// Print the files, lines, bytes and approximate tokens per directory of what would be
+    /// yanked, plus a total, instead of producing any output.
+    #[arg(long, conflicts_with_all = ["tree_only", "output_file"])]
+    pub count_only: bool,
+
+    /// How file paths are written in the headers and the table of contents: relative
+    /// (default), absolute or name (file name only). The tree is not affected.
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = PathStyle::Relative)]
+    pub path_style: PathStyle,
+
+    /// Show LABEL instead of `./` as the root of the tree in the output. Without a value the
+    /// scan root's directory name is used, e.g. `myproject/`. Pass a label as
+    /// `--prefix-tree-root=LABEL`.
+    #[arg(
+        long,
+        value_name = "LABEL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    pub prefix_tree_root: Option<String>,
+
+    /// Prefix tree entries with an emoji for their file type (📁, 🦀, 🐍, ...), in the
+    /// output tree and in the TUI. Off by default, since emoji width varies by terminal.
+    #[arg(long)]
+    pub icons: bool,
+
+    /// Abort when more than N files match the patterns and filters, as a guard against
+    /// yanking far more than intended (0 = no limit).
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub max_files: usize,
+
+    /// Skip files smaller than N bytes. Empty files are skipped whenever this is set,
+    /// even with 0.
+    #[arg(long, value_name = "N")]
+    pub min_file_size: Option<u64>,
+
+    /// Keep directories without any yanked file below them in the output tree. By default
+    /// they are pruned, so the tree matches the yanked files.
+    #[arg(long)]
+    pub include_empty_dirs: bool,
+
+    /// Add the output to the end of the current clipboard text instead of replacing it,
+    /// after a separator line. Lets several runs build up one context.
+    #[arg(long, conflicts_with = "output_file")]
+    pub append: bool,
+
+    /// Set the clipboard from the repoyank process itself instead of a background daemon on
+    /// Linux. Also enabled by setting REPOYANK_NO_CLIPBOARD_DAEMON to a non-empty value.
+    /// Without a clipboard manager the text may be gone once repoyank exits.
+    #[arg(long)]
+    pub no_clipboard_daemon: bool,
+
+    /// End the output with a summary listing every included file with its approximate token
+    /// and line count, followed by the totals.
+    #[arg(long, conflicts_with = "template")]
+    pub summary_footer: bool,
+
+    /// Print the paths that would be yanked, relative to the scan root and sorted, instead of
+    /// producing any output: one per line, or a JSON array with `--manifest=json`. No file is
+    /// read, so repoyank:skip directives and --max-file-tokens do not apply.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "lines",
+        conflicts_with_all = ["tree_only", "output_file", "count_only"]
+    )]
+    pub manifest: Option<ManifestFormat>,
+
+    /// Warn when the output exceeds about N tokens, e.g. a model's context limit.
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<usize>,
+
+    /// With --max-tokens, exit with status 5 instead of only warning (nothing is delivered).
+    #[arg(long, requires = "max_tokens")]
+    pub max_tokens_strict: bool,
+
+    /// Add a `Language: <name>` line to each file header, derived from the extension
+    /// (`Unknown` for unrecognized ones).
+    #[arg(long, conflicts_with = "template")]
+    pub label_language: bool,
+
+    /// Read newline-separated paths and globs from PATH (`-` for stdin), e.g. the output of
+    /// `rg -l` or `find`, and select those instead of everything. Paths are relative to the
+    /// scan root; listed files are taken directly, without walking the tree.
+    #[arg(long, value_name = "PATH")]
+    pub from_file: Option<std::path::PathBuf>,
+
+    /// Don't print the tree, the dry-run summary, the scan progress or the "Copied"/"Wrote"
+    /// confirmations. Errors are still reported on stderr.
+    #[arg(short = 'q', long)]
+    pub quiet: bool,
+
+    /// Don't warn about individual files: unreadable ones, and ones left out by
+    /// --max-files-per-dir, --max-file-tokens or a repoyank:skip directive.
+    #[arg(long)]
+    pub no_warnings: bool,
+
+    /// Render the output from a template file instead of the built-in layout. Placeholders:
+    /// {{path}}, {{content}}, {{lang}} (the extension) and {{index}} in the per-file
+    /// {{#file}}...{{/file}} section; {{tree}} in the optional {{#header}}...{{/header}} and
+    /// {{#footer}}...{{/footer}} sections. A template without sections is all per-file.
+    /// Unknown placeholders are an error. A name that is not an existing file is looked up in
+    /// the team .repoyank/templates/ directory.
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<std::path::PathBuf>,
+
+    /// Split the output into chunks of at most this many tokens, keeping files whole. Each
+    /// chunk repeats the tree. With --output, chunk N is written to FILE with `-N` before the
+    /// extension (`out-1.txt`, `out-2.txt`, ...); otherwise only the first chunk is copied.
+    #[arg(
+        long,
+        value_name = "TOKENS",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        conflicts_with_all = ["tree_only", "template", "tree_output"]
+    )]
+    pub split: Option<usize>,
+
+    /// Write a commented starter repoyank.toml to the current directory and exit. Refuses
+    /// to replace an existing one unless --force is given.
+    #[arg(long)]
+    pub init: bool,
+
+    /// With --init, overwrite an existing repoyank.toml.
+    #[arg(long, requires = "init")]
+    pub force: bool,
 }
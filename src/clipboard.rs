@@ -1,17 +1,28 @@
 use anyhow::Result;
 use arboard::Clipboard;
 #[cfg(target_os = "linux")]
-use arboard::SetExtLinux;
-#[cfg(target_os = "linux")]
+use arboard::{LinuxClipboardKind, SetExtLinux};
+use base64::Engine;
 
 pub const DAEMON_FLAG: &str = "__clipboard_daemon";
+// Setting this (to anything but an empty string) has the same effect as --no-clipboard-daemon.
+const NO_DAEMON_ENV: &str = "REPOYANK_NO_CLIPBOARD_DAEMON";
+// Passed to the daemon alongside DAEMON_FLAG to target the PRIMARY selection.
+#[cfg(target_os = "linux")]
+const DAEMON_PRIMARY_FLAG: &str = "__clipboard_primary";
 
 #[cfg(target_os = "linux")]
 fn run_daemon_mode() -> Result<()> {
     let text = std::io::read_to_string(std::io::stdin())?;
 
+    let kind = if std::env::args().any(|a| a == DAEMON_PRIMARY_FLAG) {
+        LinuxClipboardKind::Primary
+    } else {
+        LinuxClipboardKind::Clipboard
+    };
+
     let mut clipboard = Clipboard::new()?;
-    let result = clipboard.set().wait().text(text); // Keep the waiter alive
+    let result = clipboard.set().clipboard(kind).wait().text(text); // Keep the waiter alive
 
     match result {
         Ok(_waiter) => {
@@ -47,7 +58,184 @@ pub fn check_and_run_daemon_if_requested() -> Result<bool> {
     Ok(false)
 }
 
-pub fn copy_text_to_clipboard(text: String) -> Result<()> {
+/// How the output reaches the clipboard.
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardBackend {
+    /// Platform default: `pbcopy` on macOS (falling back to `arboard`), `arboard` elsewhere.
+    #[default]
+    Auto,
+    /// arboard. On Linux it runs in a background daemon so the clipboard outlives repoyank;
+    /// on Windows the Win32 clipboard keeps the text after the process exits by itself.
+    Arboard,
+    /// Pipe to `pbcopy` (macOS).
+    Pbcopy,
+    /// Pipe to `wl-copy` (Wayland).
+    WlCopy,
+    /// Pipe to `xclip -selection clipboard` (X11).
+    Xclip,
+    /// OSC 52 terminal escape sequence; works over SSH without a local display server.
+    Osc52,
+}
+
+// Many terminals cap OSC 52 payloads (often around 100 kB) and silently drop larger ones.
+const OSC52_WARN_BYTES: usize = 100_000;
+
+fn copy_via_osc52(text: &str, primary: bool, warnings: &mut Vec<String>) -> Result<()> {
+    use std::io::Write;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    if encoded.len() > OSC52_WARN_BYTES {
+        warnings.push(format!(
+            "OSC 52 payload is {} bytes; many terminals cap it around {} bytes and may drop or truncate it.",
+            encoded.len(),
+            OSC52_WARN_BYTES
+        ));
+    }
+    let selection = if primary { 'p' } else { 'c' };
+    let sequence = format!("\x1b]52;{};{}\x07", selection, encoded);
+    // Prefer the controlling terminal so the sequence still reaches it when stdout is piped.
+    match std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => {
+            tty.write_all(sequence.as_bytes())?;
+            tty.flush()?;
+        }
+        Err(_) => {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(sequence.as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+// Pipes `text` into an external clipboard tool and waits for it to take ownership.
+fn copy_via_command(program: &str, args: &[&str], text: &str) -> Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow::anyhow!(
+                "Clipboard tool '{}' was not found on PATH. Install it or choose another --clipboard backend.",
+                program
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    } // stdin is dropped here so the tool sees EOF.
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("'{}' exited with {}", program, status));
+    }
+    Ok(())
+}
+
+// Line between the earlier clipboard text and the output added with --append.
+const APPEND_SEPARATOR: &str = "===== appended by repoyank =====";
+
+/// The clipboard's current text for --append (the PRIMARY selection with `primary` on
+/// Linux). None when it is empty, holds something other than text, or cannot be read.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub fn read_clipboard_text(primary: bool) -> Option<String> {
+    let mut clipboard = Clipboard::new().ok()?;
+    #[cfg(target_os = "linux")]
+    let text = {
+        use arboard::GetExtLinux;
+        let kind = if primary {
+            LinuxClipboardKind::Primary
+        } else {
+            LinuxClipboardKind::Clipboard
+        };
+        clipboard.get().clipboard(kind).text()
+    };
+    #[cfg(not(target_os = "linux"))]
+    let text = clipboard.get_text();
+    text.ok().filter(|text| !text.trim().is_empty())
+}
+
+/// `previous` clipboard text followed by `output`, set apart by a separator line.
+pub fn append_output(previous: &str, output: &str) -> String {
+    format!(
+        "{}\n\n{}\n\n{}",
+        previous.trim_end(),
+        APPEND_SEPARATOR,
+        output
+    )
+}
+
+/// Whether arboard may hand the text to a background daemon on Linux: false with
+/// `--no-clipboard-daemon` or when REPOYANK_NO_CLIPBOARD_DAEMON is set to a non-empty value.
+pub fn daemon_allowed(no_daemon_flag: bool) -> bool {
+    daemon_allowed_with_env(no_daemon_flag, std::env::var_os(NO_DAEMON_ENV).as_deref())
+}
+
+// `daemon_allowed` for a given REPOYANK_NO_CLIPBOARD_DAEMON value; empty counts as unset.
+fn daemon_allowed_with_env(no_daemon_flag: bool, env_value: Option<&std::ffi::OsStr>) -> bool {
+    !no_daemon_flag && env_value.is_none_or(|value| value.is_empty())
+}
+
+/// Copies `text` using `backend`. With `primary`, the X11/Wayland PRIMARY selection
+/// (middle-click paste) is targeted instead of the regular clipboard; this is ignored
+/// with a warning on other platforms. Without `daemon`, arboard sets the text from this
+/// process on Linux too, so it may not outlive repoyank without a clipboard manager.
+///
+/// Returns the warnings for the caller to show rather than printing them, since stderr
+/// output would corrupt the TUI when it copies.
+pub fn copy_text_to_clipboard(
+    text: String,
+    backend: ClipboardBackend,
+    primary: bool,
+    daemon: bool,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    let primary = primary && primary_selection_supported(&mut warnings);
+    match backend {
+        // pbcopy hands the text to the pasteboard server, so it survives repoyank exiting
+        // independently of how arboard's pasteboard ownership behaves on a given system.
+        ClipboardBackend::Auto if cfg!(target_os = "macos") => {
+            copy_via_command("pbcopy", &[], &text).or_else(|e| {
+                warnings.push(format!("{} Falling back to arboard.", e));
+                copy_via_arboard(text, primary, daemon)
+            })
+        }
+        ClipboardBackend::Auto | ClipboardBackend::Arboard => {
+            copy_via_arboard(text, primary, daemon)
+        }
+        ClipboardBackend::Pbcopy => copy_via_command("pbcopy", &[], &text),
+        ClipboardBackend::WlCopy => {
+            let args: &[&str] = if primary { &["--primary"] } else { &[] };
+            copy_via_command("wl-copy", args, &text)
+        }
+        ClipboardBackend::Xclip => {
+            let selection = if primary { "primary" } else { "clipboard" };
+            copy_via_command("xclip", &["-selection", selection], &text)
+        }
+        ClipboardBackend::Osc52 => copy_via_osc52(&text, primary, &mut warnings),
+    }?;
+    Ok(warnings)
+}
+
+fn primary_selection_supported(warnings: &mut Vec<String>) -> bool {
+    if cfg!(target_os = "linux") {
+        true
+    } else {
+        warnings.push("--primary only applies on Linux. Copying to the regular clipboard.".into());
+        false
+    }
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn copy_via_arboard(text: String, primary: bool, daemon: bool) -> Result<()> {
     #[cfg(not(target_os = "linux"))]
     {
         let mut clipboard = Clipboard::new()?;
@@ -55,7 +243,15 @@ pub fn copy_text_to_clipboard(text: String) -> Result<()> {
     }
 
     #[cfg(target_os = "linux")]
-    {
+    if !daemon {
+        let kind = if primary {
+            LinuxClipboardKind::Primary
+        } else {
+            LinuxClipboardKind::Clipboard
+        };
+        let mut clipboard = Clipboard::new()?;
+        clipboard.set().clipboard(kind).text(text)?;
+    } else {
         use std::io::Write;
         use std::process::{Command, Stdio};
 
@@ -63,8 +259,12 @@ pub fn copy_text_to_clipboard(text: String) -> Result<()> {
         // or if a portal is preferred. `arboard` tries to handle this, but for the daemon approach,
         // we are manually forking.
 
-        let mut child = Command::new(std::env::current_exe()?)
-            .arg(DAEMON_FLAG)
+        let mut command = Command::new(std::env::current_exe()?);
+        command.arg(DAEMON_FLAG);
+        if primary {
+            command.arg(DAEMON_PRIMARY_FLAG);
+        }
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::null())
             .stderr(Stdio::null())
@@ -80,3 +280,28 @@ pub fn copy_text_to_clipboard(text: String) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn the_daemon_is_allowed_by_default() {
+        assert!(daemon_allowed_with_env(false, None));
+    }
+
+    #[test]
+    fn the_flag_disables_the_daemon() {
+        assert!(!daemon_allowed_with_env(true, None));
+        assert!(!daemon_allowed_with_env(true, Some(OsStr::new(""))));
+    }
+
+    #[test]
+    fn a_non_empty_env_var_disables_the_daemon() {
+        assert!(!daemon_allowed_with_env(false, Some(OsStr::new("1"))));
+        assert!(!daemon_allowed_with_env(false, Some(OsStr::new("0"))));
+        // Set but empty counts as unset.
+        assert!(daemon_allowed_with_env(false, Some(OsStr::new(""))));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::utils;
+use std::path::Path;
+
+/// Comment and string syntax of a language family, as far as stripping needs it.
+struct Syntax {
+    line: Option<&'static str>,
+    block: Option<(&'static str, &'static str)>,
+    /// The line marker only counts at the start of a word (`#` in shell: `$#`, `${#x}`).
+    line_needs_word_start: bool,
+    /// `"""`/`'''` strings that may span lines.
+    triple_quotes: bool,
+    /// `` ` `` delimits strings that may span lines (JS templates, Go raw strings).
+    backtick_strings: bool,
+    /// Rust: `'` is a lifetime unless it forms a char literal; raw strings `r#"…"#`;
+    /// ordinary strings may span lines.
+    rust: bool,
+}
+
+const C_LIKE: Syntax = Syntax {
+    line: Some("//"),
+    block: Some(("/*", "*/")),
+    line_needs_word_start: false,
+    triple_quotes: true,
+    backtick_strings: false,
+    rust: false,
+};
+
+fn syntax_for_extension(ext: &str) -> Option<Syntax> {
+    let syntax = match ext {
+        "rs" => Syntax {
+            rust: true,
+            triple_quotes: false,
+            ..C_LIKE
+        },
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "go" => Syntax {
+            backtick_strings: true,
+            triple_quotes: false,
+            ..C_LIKE
+        },
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "java" | "kt" | "kts" | "scala"
+        | "swift" | "cs" | "dart" | "scss" | "less" => C_LIKE,
+        // Plain CSS has no line comments, and `//` appears in unquoted url(…) values.
+        "css" => Syntax {
+            line: None,
+            ..C_LIKE
+        },
+        "py" | "pyi" | "toml" => Syntax {
+            line: Some("#"),
+            block: None,
+            line_needs_word_start: true,
+            ..C_LIKE
+        },
+        "sh" | "bash" | "zsh" | "rb" | "pl" | "pm" | "r" | "yaml" | "yml" | "cmake" | "ex"
+        | "exs" => Syntax {
+            line: Some("#"),
+            block: None,
+            line_needs_word_start: true,
+            triple_quotes: false,
+            ..C_LIKE
+        },
+        "sql" => Syntax {
+            line: Some("--"),
+            triple_quotes: false,
+            ..C_LIKE
+        },
+        "hs" => Syntax {
+            line: Some("--"),
+            block: Some(("{-", "-}")),
+            triple_quotes: false,
+            ..C_LIKE
+        },
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+enum State {
+    Code,
+    /// Inside a string closed by this quote character.
+    Quoted(char),
+    TripleQuoted(char),
+    /// Inside a Rust raw string with this many `#`s.
+    RawString(usize),
+    BlockComment,
+}
+
+fn starts_with_at(chars: &[char], at: usize, marker: &str) -> bool {
+    let marker_len = marker.chars().count();
+    chars
+        .get(at..at + marker_len)
+        .is_some_and(|window| window.iter().copied().eq(marker.chars()))
+}
+
+/// Removes comments from `contents`, picking the syntax from the file extension. Returns None
+/// for languages it does not know. Line breaks are kept, so every line stays at its original
+/// line number; lines that held only a comment become empty.
+///
+/// This is a lexer-level pass, not a parser: it tracks string literals so comment markers
+/// inside them survive, and errs towards leaving text alone when unsure.
+pub fn strip_comments(path: &Path, contents: &str) -> Option<String> {
+    let syntax = syntax_for_extension(&utils::extension_key(path))?;
+    let chars: Vec<char> = contents.chars().collect();
+    let mut output = String::with_capacity(contents.len());
+    // Lines that lost a comment get their trailing whitespace trimmed afterwards.
+    let mut stripped_lines: Vec<bool> = vec![false];
+    let mut state = State::Code;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            output.push('\n');
+            stripped_lines.push(false);
+            i += 1;
+            // Ordinary strings end at the line break, except in Rust. Resetting limits the
+            // damage of a quote that was misread as opening a string.
+            if matches!(state, State::Quoted(q) if q != '`') && !syntax.rust {
+                state = State::Code;
+            }
+            continue;
+        }
+        match state {
+            State::Code => {
+                if let Some((open, _)) = syntax.block
+                    && starts_with_at(&chars, i, open)
+                {
+                    state = State::BlockComment;
+                    *stripped_lines.last_mut().unwrap() = true;
+                    i += open.chars().count();
+                    continue;
+                }
+                if let Some(marker) = syntax.line
+                    && starts_with_at(&chars, i, marker)
+                    && (!syntax.line_needs_word_start || i == 0 || chars[i - 1].is_whitespace())
+                    && !(i == 0 && marker == "#" && chars.get(1) == Some(&'!'))
+                {
+                    // Drop the rest of the line; the newline itself is handled above.
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                    *stripped_lines.last_mut().unwrap() = true;
+                    continue;
+                }
+                if syntax.triple_quotes
+                    && (c == '"' || c == '\'')
+                    && chars.get(i + 1) == Some(&c)
+                    && chars.get(i + 2) == Some(&c)
+                {
+                    output.extend([c, c, c]);
+                    state = State::TripleQuoted(c);
+                    i += 3;
+                    continue;
+                }
+                if syntax.rust
+                    && c == 'r'
+                    && (i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_'))
+                {
+                    let hashes = chars[i + 1..].iter().take_while(|&&ch| ch == '#').count();
+                    if chars.get(i + 1 + hashes) == Some(&'"') {
+                        output.extend(&chars[i..i + hashes + 2]);
+                        state = State::RawString(hashes);
+                        i += hashes + 2;
+                        continue;
+                    }
+                }
+                if syntax.rust && c == '\'' {
+                    // Char literals are `'x'` or `'\…'`; anything else is a lifetime or label.
+                    let literal_len = if chars.get(i + 1) == Some(&'\\') {
+                        chars
+                            .get(i + 3..)
+                            .unwrap_or_default()
+                            .iter()
+                            .take(10)
+                            .position(|&ch| ch == '\'')
+                            .map(|pos| pos + 4)
+                    } else if chars.get(i + 2) == Some(&'\'') {
+                        Some(3)
+                    } else {
+                        None
+                    };
+                    let len = literal_len.unwrap_or(1);
+                    output.extend(&chars[i..i + len]);
+                    i += len;
+                    continue;
+                }
+                if c == '"' || c == '\'' || (c == '`' && syntax.backtick_strings) {
+                    state = State::Quoted(c);
+                }
+                output.push(c);
+                i += 1;
+            }
+            State::Quoted(quote) => {
+                output.push(c);
+                if c == '\\' && quote != '`' {
+                    if let Some(&escaped) = chars.get(i + 1)
+                        && escaped != '\n'
+                    {
+                        output.push(escaped);
+                        i += 1;
+                    }
+                } else if c == quote {
+                    state = State::Code;
+                }
+                i += 1;
+            }
+            State::TripleQuoted(quote) => {
+                if c == quote
+                    && chars.get(i + 1) == Some(&quote)
+                    && chars.get(i + 2) == Some(&quote)
+                {
+                    output.extend([quote, quote, quote]);
+                    state = State::Code;
+                    i += 3;
+                    continue;
+                }
+                output.push(c);
+                if c == '\\'
+                    && let Some(&escaped) = chars.get(i + 1)
+                    && escaped != '\n'
+                {
+                    output.push(escaped);
+                    i += 1;
+                }
+                i += 1;
+            }
+            State::RawString(hashes) => {
+                output.push(c);
+                i += 1;
+                if c == '"'
+                    && chars[i..]
+                        .iter()
+                        .take(hashes)
+                        .filter(|&&ch| ch == '#')
+                        .count()
+                        == hashes
+                {
+                    output.extend(&chars[i..i + hashes]);
+                    i += hashes;
+                    state = State::Code;
+                }
+            }
+            State::BlockComment => {
+                let (_, close) = syntax
+                    .block
+                    .expect("block comment state without block syntax");
+                if starts_with_at(&chars, i, close) {
+                    state = State::Code;
+                    i += close.chars().count();
+                } else {
+                    i += 1;
+                }
+                *stripped_lines.last_mut().unwrap() = true;
+            }
+        }
+    }
+
+    let stripped = output
+        .split('\n')
+        .zip(stripped_lines)
+        .map(|(line, was_stripped)| if was_stripped { line.trim_end() } else { line })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(stripped)
+}
@@ -0,0 +1,392 @@
+use crate::cli;
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LOCAL_CONFIG_FILE: &str = "repoyank.toml";
+/// A committed directory of shared team settings, found in the scan root or an ancestor.
+pub const TEAM_DIR: &str = ".repoyank";
+const TEAM_CONFIG_FILE: &str = "config.toml";
+const TEAM_TEMPLATES_DIR: &str = "templates";
+
+/// Defaults read from `repoyank.toml`. Keys are the long option names, e.g.
+/// `type = ["rs", "md"]` or `max-file-tokens = 5000`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(rename = "type")]
+    pub type_filter: Option<Vec<String>>,
+    #[serde(rename = "exclude-type")]
+    pub exclude_types: Option<Vec<String>>,
+    #[serde(rename = "exclude")]
+    pub exclude_globs: Option<Vec<String>>,
+    pub no_default_excludes: Option<bool>,
+    pub include_ignored: Option<bool>,
+    pub include_ignored_globs: Option<Vec<String>>,
+    pub hidden: Option<bool>,
+    pub follow_symlinks: Option<bool>,
+    pub depth: Option<usize>,
+    pub no_tree: Option<bool>,
+    pub tree_tokens: Option<bool>,
+    pub compact_tree: Option<bool>,
+    pub toc: Option<bool>,
+    pub group_by_ext: Option<bool>,
+    pub sort: Option<cli::SortOrder>,
+    pub dirs_first: Option<bool>,
+    pub output_order: Option<cli::OutputOrder>,
+    pub paths_from_cwd: Option<bool>,
+    pub max_file_tokens: Option<usize>,
+    pub max_files_per_dir: Option<usize>,
+    pub include_binary_base64: Option<bool>,
+    pub max_binary_bytes: Option<u64>,
+    pub lossy: Option<bool>,
+    pub clipboard: Option<crate::clipboard::ClipboardBackend>,
+    pub clipboard_max_bytes: Option<u64>,
+    pub primary: Option<bool>,
+    pub ignore_directives: Option<bool>,
+    pub theme: Option<cli::Theme>,
+    pub path_style: Option<cli::PathStyle>,
+    pub icons: Option<bool>,
+    pub max_files: Option<usize>,
+    pub min_file_size: Option<u64>,
+    pub include_empty_dirs: Option<bool>,
+    pub summary_footer: Option<bool>,
+    pub max_tokens: Option<usize>,
+    pub max_tokens_strict: Option<bool>,
+    pub label_language: Option<bool>,
+    /// Named extension lists usable wherever `--type`/`--exclude-type` take an extension,
+    /// e.g. `web = ["js", "ts", "css"]`. Not a command line option.
+    pub type_groups: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl Config {
+    /// Reads a config file; a missing file is an empty config.
+    fn load(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Could not read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Combines two configs key by key, with `self` taking precedence over `fallback`.
+    fn or(self, fallback: Config) -> Config {
+        Config {
+            type_filter: self.type_filter.or(fallback.type_filter),
+            exclude_types: self.exclude_types.or(fallback.exclude_types),
+            exclude_globs: self.exclude_globs.or(fallback.exclude_globs),
+            no_default_excludes: self.no_default_excludes.or(fallback.no_default_excludes),
+            include_ignored: self.include_ignored.or(fallback.include_ignored),
+            include_ignored_globs: self
+                .include_ignored_globs
+                .or(fallback.include_ignored_globs),
+            hidden: self.hidden.or(fallback.hidden),
+            follow_symlinks: self.follow_symlinks.or(fallback.follow_symlinks),
+            depth: self.depth.or(fallback.depth),
+            no_tree: self.no_tree.or(fallback.no_tree),
+            tree_tokens: self.tree_tokens.or(fallback.tree_tokens),
+            compact_tree: self.compact_tree.or(fallback.compact_tree),
+            toc: self.toc.or(fallback.toc),
+            group_by_ext: self.group_by_ext.or(fallback.group_by_ext),
+            sort: self.sort.or(fallback.sort),
+            dirs_first: self.dirs_first.or(fallback.dirs_first),
+            output_order: self.output_order.or(fallback.output_order),
+            paths_from_cwd: self.paths_from_cwd.or(fallback.paths_from_cwd),
+            max_file_tokens: self.max_file_tokens.or(fallback.max_file_tokens),
+            max_files_per_dir: self.max_files_per_dir.or(fallback.max_files_per_dir),
+            include_binary_base64: self
+                .include_binary_base64
+                .or(fallback.include_binary_base64),
+            max_binary_bytes: self.max_binary_bytes.or(fallback.max_binary_bytes),
+            lossy: self.lossy.or(fallback.lossy),
+            clipboard: self.clipboard.or(fallback.clipboard),
+            clipboard_max_bytes: self.clipboard_max_bytes.or(fallback.clipboard_max_bytes),
+            primary: self.primary.or(fallback.primary),
+            ignore_directives: self.ignore_directives.or(fallback.ignore_directives),
+            theme: self.theme.or(fallback.theme),
+            path_style: self.path_style.or(fallback.path_style),
+            icons: self.icons.or(fallback.icons),
+            max_files: self.max_files.or(fallback.max_files),
+            min_file_size: self.min_file_size.or(fallback.min_file_size),
+            include_empty_dirs: self.include_empty_dirs.or(fallback.include_empty_dirs),
+            summary_footer: self.summary_footer.or(fallback.summary_footer),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            max_tokens_strict: self.max_tokens_strict.or(fallback.max_tokens_strict),
+            label_language: self.label_language.or(fallback.label_language),
+            // Groups merge by name, so a team file can add to the global groups.
+            type_groups: match (self.type_groups, fallback.type_groups) {
+                (Some(groups), Some(mut fallback_groups)) => {
+                    fallback_groups.extend(groups);
+                    Some(fallback_groups)
+                }
+                (groups, fallback_groups) => groups.or(fallback_groups),
+            },
+        }
+    }
+}
+
+/// What `--init` writes: every line a comment or a working example, so the file is valid
+/// as generated.
+const STARTER_CONFIG: &str = r#"# repoyank defaults for this directory. Keys are the long option names; options given
+# on the command line always win. See the README for every supported key.
+
+# Only offer these extensions. Entries can also name a type group (below).
+# type = ["rs", "toml", "md"]
+
+# Drop files with these extensions, after `type`.
+exclude-type = ["lock"]
+
+# Skip paths matching these globs, relative to the scan root. A glob without "/" matches at
+# any depth. Added to the built-in excludes (.git, node_modules, target, dist, ...).
+# exclude = ["fixtures", "docs/generated"]
+
+# Order of the TUI list and the output: name, size, mtime or ext.
+# sort = "name"
+
+# Prefix the output with a numbered index of the files.
+# toc = false
+
+# Skip files estimated above this many tokens.
+# max-file-tokens = 8000
+
+# Warn when the whole output is estimated above this many tokens.
+# max-tokens = 100000
+
+# Named extension lists, usable in `type`, `exclude-type`, --type and --exclude-type.
+[type-groups]
+web = ["js", "ts", "css", "html"]
+docs = ["md", "rst", "txt"]
+"#;
+
+/// Writes a commented starter `repoyank.toml` to `dir`. An existing file is only replaced
+/// with `force`. Returns the path written.
+pub fn write_starter_config(dir: &Path, force: bool) -> Result<PathBuf> {
+    let path = dir.join(LOCAL_CONFIG_FILE);
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    fs::write(&path, STARTER_CONFIG)
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    Ok(path)
+}
+
+/// `$XDG_CONFIG_HOME/repoyank/config.toml`, falling back to `~/.config`.
+fn global_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("repoyank").join("config.toml"))
+}
+
+/// The nearest `.repoyank/` directory, looking in the scan root and then its ancestors.
+pub fn find_team_dir(scan_root: &Path) -> Option<PathBuf> {
+    let start = scan_root.canonicalize().ok()?;
+    start
+        .ancestors()
+        .map(|dir| dir.join(TEAM_DIR))
+        .find(|candidate| candidate.is_dir())
+}
+
+/// Loads `repoyank.toml` from the scan root on top of the team `.repoyank/config.toml`, on
+/// top of the global config.
+pub fn load_config(scan_root: &Path) -> Result<Config> {
+    let global = match global_config_path() {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+    let team = match find_team_dir(scan_root) {
+        Some(dir) => Config::load(&dir.join(TEAM_CONFIG_FILE))?,
+        None => Config::default(),
+    };
+    let local = Config::load(&scan_root.join(LOCAL_CONFIG_FILE))?;
+    Ok(local.or(team.or(global)))
+}
+
+/// Resolves a `--template` argument. A path that exists is used as is; otherwise the name
+/// is looked up in the team `.repoyank/templates/`, with or without its extension.
+pub fn resolve_template(template: &Path, scan_root: &Path) -> PathBuf {
+    if template.exists() {
+        return template.to_path_buf();
+    }
+    let Some(templates_dir) = find_team_dir(scan_root).map(|dir| dir.join(TEAM_TEMPLATES_DIR))
+    else {
+        return template.to_path_buf();
+    };
+    let exact = templates_dir.join(template);
+    if exact.is_file() {
+        return exact;
+    }
+    let by_stem = fs::read_dir(&templates_dir).ok().and_then(|entries| {
+        let mut matches: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.file_stem() == Some(template.as_os_str()))
+            .collect();
+        matches.sort();
+        matches.into_iter().next()
+    });
+    by_stem.unwrap_or_else(|| template.to_path_buf())
+}
+
+// Replaces every entry that names a type group with the group's extensions.
+fn expand_type_groups(types: &mut Vec<String>, groups: &BTreeMap<String, Vec<String>>) {
+    *types = types
+        .drain(..)
+        .flat_map(|entry| match groups.get(&entry) {
+            Some(extensions) => extensions.clone(),
+            None => vec![entry],
+        })
+        .collect();
+}
+
+/// Fills in every option that was not given on the command line from `config`.
+/// Precedence is command line > local config > team config > global config > built-in
+/// default. Type group names in `--type`/`--exclude-type` are expanded last.
+pub fn apply_config(cli_args: &mut cli::Cli, matches: &ArgMatches, config: Config) {
+    let type_groups = config.type_groups.unwrap_or_default();
+    let from_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    // Config keys, `Cli` fields and clap argument ids share their names.
+    macro_rules! fill {
+        ($($field:ident),*) => {$(
+            if let Some(value) = config.$field
+                && !from_command_line(stringify!($field))
+            {
+                cli_args.$field = value;
+            }
+        )*};
+    }
+    macro_rules! fill_optional {
+        ($($field:ident),*) => {$(
+            if let Some(value) = config.$field
+                && !from_command_line(stringify!($field))
+            {
+                cli_args.$field = Some(value);
+            }
+        )*};
+    }
+    fill!(
+        type_filter,
+        exclude_types,
+        exclude_globs,
+        no_default_excludes,
+        include_ignored,
+        include_ignored_globs,
+        hidden,
+        follow_symlinks,
+        no_tree,
+        tree_tokens,
+        compact_tree,
+        toc,
+        group_by_ext,
+        sort,
+        dirs_first,
+        output_order,
+        paths_from_cwd,
+        include_binary_base64,
+        max_binary_bytes,
+        lossy,
+        clipboard,
+        clipboard_max_bytes,
+        primary,
+        ignore_directives,
+        theme,
+        path_style,
+        icons,
+        max_files,
+        include_empty_dirs,
+        summary_footer,
+        max_tokens_strict,
+        label_language
+    );
+    fill_optional!(
+        depth,
+        max_file_tokens,
+        max_files_per_dir,
+        min_file_size,
+        max_tokens
+    );
+    // --tree-only conflicts with --no-tree on the command line; a configured no-tree yields.
+    if cli_args.tree_only {
+        cli_args.no_tree = false;
+    }
+    // The same goes for the sections a --template replaces.
+    if cli_args.template.is_some() {
+        cli_args.toc = false;
+        cli_args.group_by_ext = false;
+        cli_args.summary_footer = false;
+        cli_args.label_language = false;
+    }
+    expand_type_groups(&mut cli_args.type_filter, &type_groups);
+    expand_type_groups(&mut cli_args.exclude_types, &type_groups);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn config(text: &str) -> Config {
+        toml::from_str(text).unwrap()
+    }
+
+    // The parsed options and raw matches for a command line, as `main` builds them.
+    fn command_line(args: &[&str]) -> (cli::Cli, ArgMatches) {
+        let matches = cli::Cli::command()
+            .try_get_matches_from(std::iter::once("repoyank").chain(args.iter().copied()))
+            .unwrap();
+        (cli::Cli::from_arg_matches(&matches).unwrap(), matches)
+    }
+
+    #[test]
+    fn or_takes_each_key_from_the_first_config_that_sets_it() {
+        let local = config("max-file-tokens = 100\ntoc = true");
+        let team = config("max-file-tokens = 200\ndepth = 3\nsort = \"size\"");
+        let global = config("depth = 5\nhidden = true\ntoc = false");
+        let merged = local.or(team.or(global));
+        assert_eq!(merged.max_file_tokens, Some(100));
+        assert_eq!(merged.toc, Some(true));
+        assert_eq!(merged.depth, Some(3));
+        assert_eq!(merged.sort, Some(cli::SortOrder::Size));
+        assert_eq!(merged.hidden, Some(true));
+        assert_eq!(merged.no_tree, None);
+    }
+
+    #[test]
+    fn or_merges_type_groups_by_name() {
+        let team = config("[type-groups]\nweb = [\"js\"]");
+        let global = config("[type-groups]\nweb = [\"ts\"]\ndocs = [\"md\"]");
+        let groups = team.or(global).type_groups.unwrap();
+        assert_eq!(groups["web"], vec!["js"]);
+        assert_eq!(groups["docs"], vec!["md"]);
+    }
+
+    #[test]
+    fn the_command_line_wins_over_the_config() {
+        let (mut cli_args, matches) = command_line(&["--max-file-tokens", "7", "--sort", "mtime"]);
+        let config = config("max-file-tokens = 100\nsort = \"size\"\ntoc = true\ndepth = 2");
+        apply_config(&mut cli_args, &matches, config);
+        assert_eq!(cli_args.max_file_tokens, Some(7));
+        assert_eq!(cli_args.sort, cli::SortOrder::Mtime);
+        // Keys not given on the command line come from the config.
+        assert!(cli_args.toc);
+        assert_eq!(cli_args.depth, Some(2));
+    }
+
+    #[test]
+    fn a_closer_config_can_turn_a_switch_off_again() {
+        let (mut cli_args, matches) = command_line(&[]);
+        let config = config("toc = false\ntype = [\"web\"]")
+            .or(config("toc = true\n[type-groups]\nweb = [\"js\", \"css\"]"));
+        apply_config(&mut cli_args, &matches, config);
+        assert!(!cli_args.toc);
+        assert_eq!(cli_args.type_filter, vec!["js", "css"]);
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+const DIRECTIVE_PREFIX: &str = "repoyank:";
+/// Directives are only honoured near the top of a file.
+const DIRECTIVE_SCAN_LINES: usize = 10;
+
+/// Per-file output controls read from `repoyank:` magic comments, e.g.
+/// `// repoyank:max-lines=50` or `# repoyank:head=20 repoyank:tail=10`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileDirectives {
+    /// `repoyank:skip`: leave the file out of the output entirely.
+    pub skip: bool,
+    /// `repoyank:max-lines=N`: keep only the first N lines.
+    pub max_lines: Option<usize>,
+    /// `repoyank:head=N`: keep the first N lines.
+    pub head: Option<usize>,
+    /// `repoyank:tail=N`: keep the last N lines.
+    pub tail: Option<usize>,
+}
+
+impl FileDirectives {
+    /// Collects the directives in the first lines of `contents`. Any comment syntax works,
+    /// since only whitespace-separated `repoyank:` tokens are looked at. Malformed
+    /// directives are ignored, and reported on stderr if `warn`.
+    pub fn parse(path: &Path, contents: &str, warn: bool) -> Self {
+        let mut directives = FileDirectives::default();
+        for token in contents
+            .lines()
+            .take(DIRECTIVE_SCAN_LINES)
+            .flat_map(str::split_whitespace)
+        {
+            let Some(directive) = token.strip_prefix(DIRECTIVE_PREFIX) else {
+                continue;
+            };
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (directive, None),
+            };
+            let count = value.and_then(|value| value.parse::<usize>().ok());
+            match (name, value, count) {
+                ("skip", None, _) => directives.skip = true,
+                ("max-lines", Some(_), Some(count)) => directives.max_lines = Some(count),
+                ("head", Some(_), Some(count)) => directives.head = Some(count),
+                ("tail", Some(_), Some(count)) => directives.tail = Some(count),
+                _ if warn => eprintln!(
+                    "⚠️ Warning: Ignoring unrecognised directive `{}` in {}",
+                    token,
+                    path.display()
+                ),
+                _ => {}
+            }
+        }
+        directives
+    }
+
+    /// The directives of the file at `path`, reading only the lines they may appear on.
+    /// Invalid UTF-8 is replaced; an unreadable file has no directives. Nothing is reported.
+    pub fn read_head(path: &Path) -> Self {
+        let Ok(file) = File::open(path) else {
+            return FileDirectives::default();
+        };
+        let mut head = String::new();
+        for line in BufReader::new(file)
+            .split(b'\n')
+            .take(DIRECTIVE_SCAN_LINES)
+            .map_while(Result::ok)
+        {
+            head.push_str(&String::from_utf8_lossy(&line));
+            head.push('\n');
+        }
+        FileDirectives::parse(path, &head, false)
+    }
+
+    /// Returns the truncated contents, or None when the directives leave the file as is.
+    /// `head`/`tail` take precedence over `max-lines`; with both, the two ends are kept.
+    pub fn truncate(&self, contents: &str) -> Option<String> {
+        let lines: Vec<&str> = contents.trim_end().lines().collect();
+        let (head, tail) = match (self.head, self.tail) {
+            (None, None) => (self.max_lines?, 0),
+            (head, tail) => (head.unwrap_or(0), tail.unwrap_or(0)),
+        };
+        if head + tail >= lines.len() {
+            return None;
+        }
+        let omitted = lines.len() - head - tail;
+        let mut kept: Vec<&str> = lines[..head].to_vec();
+        let marker = format!("[… {} lines omitted by a repoyank directive …]", omitted);
+        kept.push(&marker);
+        kept.extend_from_slice(&lines[lines.len() - tail..]);
+        Some(kept.join("\n"))
+    }
+}
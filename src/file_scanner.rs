@@ -1,64 +1,565 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use glob::Pattern;
 use ignore::WalkBuilder;
-use std::ffi::OsStr;
+use ignore::overrides::OverrideBuilder;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+/// The progress line is redrawn every this many entries, so small walks never show it.
+const PROGRESS_INTERVAL: usize = 1000;
+/// Carriage return plus "erase line": wipes the progress line from the terminal.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+/// Extra `--exclude` globs, comma-separated, added to those from the command line or config.
+const EXCLUDE_ENV: &str = "REPOYANK_EXCLUDE";
+
+/// Directories that are almost never wanted in a yank: VCS metadata, dependency trees, build
+/// output and repoyank's own team settings. Pruned from every walk unless `--no-default-excludes` is given.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "dist",
+    "__pycache__",
+    ".venv",
+    ".tox",
+    crate::config::TEAM_DIR,
+];
+
+/// Options controlling which entries the directory walk visits.
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Disable .gitignore/.ignore rules.
+    pub include_ignored: bool,
+    /// Ignored files matching these .gitignore-style globs are visited anyway, along with
+    /// their parent directories. Unused with `include_ignored`.
+    pub include_ignored_globs: Vec<String>,
+    /// Visit dotfiles and dot-directories (still subject to ignore rules).
+    pub hidden: bool,
+    pub follow_symlinks: bool,
+    pub max_depth: Option<usize>,
+    /// Entries whose path relative to the root matches one of these are skipped, and
+    /// directories are not descended into. Applies even with `include_ignored`.
+    pub excludes: Vec<Pattern>,
+    /// Report the number of entries walked on stderr while scanning large trees. Only drawn
+    /// when stderr is a terminal, and erased once the walk is done.
+    pub progress: bool,
+}
+
+/// Compiles the exclude globs: the built-in [`DEFAULT_EXCLUDES`] unless `no_defaults`, then
+/// `user_globs` and REPOYANK_EXCLUDE. A glob without a `/` matches at any depth, so `target`
+/// prunes `crates/foo/target` too.
+pub fn exclude_patterns(user_globs: &[String], no_defaults: bool) -> Result<Vec<Pattern>> {
+    let from_env: Vec<String> = std::env::var(EXCLUDE_ENV)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|glob| !glob.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    let defaults = DEFAULT_EXCLUDES
+        .iter()
+        .filter(|_| !no_defaults)
+        .map(|glob| glob.to_string());
+
+    defaults
+        .chain(user_globs.iter().cloned())
+        .chain(from_env)
+        .map(|glob| {
+            let anchored = if glob.contains('/') {
+                glob.trim_start_matches("./").to_string()
+            } else {
+                format!("**/{}", glob)
+            };
+            Pattern::new(&anchored).with_context(|| format!("Invalid --exclude glob '{}'", glob))
+        })
+        .collect()
+}
+
+/// A path found by the walk, with the metadata captured while visiting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScannedEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// File size in bytes; 0 for directories and entries that could not be statted.
+    pub size: u64,
+    /// Last modification time; `None` for directories and entries that could not be statted.
+    pub modified: Option<SystemTime>,
+    /// Whether the path itself is a symlink, so its real file may also appear elsewhere.
+    pub is_symlink: bool,
+}
+
+impl ScannedEntry {
+    /// A directory the walk did not stat, e.g. an ancestor of a `--from-file` path.
+    pub fn directory(path: PathBuf) -> Self {
+        ScannedEntry {
+            path,
+            is_dir: true,
+            size: 0,
+            modified: None,
+            is_symlink: false,
+        }
+    }
+
+    /// The `(path, is_dir)` pair most of the pipeline works with.
+    pub fn into_path_and_kind(self) -> (PathBuf, bool) {
+        (self.path, self.is_dir)
+    }
+}
+
+// Whether `path`'s file name ends in one of the `types` extensions. Matching is
+// case-insensitive (`-t md` keeps README.MD), and `ends_with` keeps compound extensions
+// like `tar.gz` working.
+fn has_extension_in(path: &Path, types: &[String]) -> bool {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    types.iter().any(|ext_filter_str| {
+        let ext_with_dot = format!(".{}", ext_filter_str.to_lowercase());
+        file_name.ends_with(&ext_with_dot)
+    })
+}
+
+/// Whether a file passes --type and --exclude-type: its extension is in `types_filter` (or
+/// that is empty) and not in `exclude_types`.
+pub fn matches_type_filters(
+    path: &Path,
+    types_filter: &[String],
+    exclude_types: &[String],
+) -> bool {
+    (types_filter.is_empty() || has_extension_in(path, types_filter))
+        && !has_extension_in(path, exclude_types)
+}
+
+/// Walks `root`. Files are kept when they pass [`matches_type_filters`]; directories are
+/// always kept. Entries the walk could not visit, such as symlink loops, are reported as
+/// warnings on stderr.
 pub fn scan_files(
     root: &Path,
     types_filter: &[String],
-    include_ignored: bool,
-) -> Result<Vec<(PathBuf, bool)>> {
-    let mut collected_paths: Vec<(PathBuf, bool)> = Vec::new();
-    let mut walker = WalkBuilder::new(root);
-
-    if include_ignored {
-        walker.git_ignore(false).ignore(false);
+    exclude_types: &[String],
+    options: &ScanOptions,
+) -> Result<Vec<ScannedEntry>> {
+    let (entries, warnings) = walk(root, types_filter, exclude_types, options)?;
+    for warning in warnings {
+        eprintln!("⚠️  Warning during scan: {}", warning);
     }
+    Ok(entries)
+}
+
+// The walk behind `scan_files`, returning the walk errors instead of printing them.
+fn walk(
+    root: &Path,
+    types_filter: &[String],
+    exclude_types: &[String],
+    options: &ScanOptions,
+) -> Result<(Vec<ScannedEntry>, Vec<ignore::Error>)> {
+    let mut warnings: Vec<ignore::Error> = Vec::new();
+    let mut collected_paths: Vec<ScannedEntry> = Vec::new();
+    let walker = configured_walker(root, options, options.include_ignored, None);
+
+    // Overrides are a whitelist: once one is set, every file it does not match is dropped.
+    // So the un-ignored files come from a second walk that disregards the ignore rules and
+    // keeps only what the globs match, rather than from the main walk. It only descends
+    // towards the directories the globs name, so `build/*.txt` walks `build/` alone.
+    let unignored_walk = if !options.include_ignored && !options.include_ignored_globs.is_empty() {
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &options.include_ignored_globs {
+            overrides
+                .add(glob)
+                .with_context(|| format!("Invalid --include-ignored-globs glob '{}'", glob))?;
+        }
+        let leading_dirs = glob_leading_dirs(&options.include_ignored_globs);
+        let mut unignored_walker = configured_walker(root, options, true, leading_dirs);
+        unignored_walker.overrides(
+            overrides
+                .build()
+                .context("Invalid --include-ignored-globs globs")?,
+        );
+        Some(unignored_walker.build())
+    } else {
+        None
+    };
+
     // Ensure the root directory itself is always included if it exists,
     // especially if it's empty or only contains filtered-out files.
     // It's important for build_tree_labels to have the root.
     if root.exists() && root.is_dir() {
-        collected_paths.push((root.to_path_buf(), true));
+        collected_paths.push(ScannedEntry::directory(root.to_path_buf()));
     }
 
-    for result in walker.build() {
+    let show_progress = options.progress && std::io::stderr().is_terminal();
+    let mut visited: usize = 0;
+    let mut progress_shown = false;
+    let main_walk = walker.build().map(|result| (result, false));
+    let extra_walk = unignored_walk
+        .into_iter()
+        .flatten()
+        .map(|result| (result, true));
+    for (result, unignored) in main_walk.chain(extra_walk) {
+        visited += 1;
+        if show_progress && visited.is_multiple_of(PROGRESS_INTERVAL) {
+            eprint!("{}⏳ Scanning... {} entries", CLEAR_LINE, visited);
+            progress_shown = true;
+        }
         let dirent = match result {
             Ok(v) => v,
             Err(e) => {
-                eprintln!("⚠️  Warning during scan: {}", e);
+                warnings.push(e);
                 continue;
             }
         };
 
+        // Skip the root path itself if already added, to avoid duplicates from walker
+        if dirent.path() == root {
+            continue;
+        }
+
+        // One stat per entry gives both the kind and the size. Symlinks are judged by their
+        // target, so a link to a directory counts as a directory even when not followed.
+        let is_symlink = dirent.path_is_symlink();
+        let metadata = if is_symlink {
+            std::fs::metadata(dirent.path()).ok()
+        } else {
+            dirent.metadata().ok()
+        };
+        let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+        let file_metadata = metadata.filter(|m| !m.is_dir());
+        let size = file_metadata.as_ref().map_or(0, |m| m.len());
+        let modified = file_metadata.and_then(|m| m.modified().ok());
         let path = dirent.into_path();
 
-        // Skip the root path itself if already added, to avoid duplicates from walker
-        if path == root {
+        // The second walk descends into every directory; only those leading to an
+        // un-ignored file are kept, added below.
+        if unignored && is_dir {
             continue;
         }
+        // The type filters apply only to files; --exclude-type removes from what --type kept.
+        if !is_dir && !matches_type_filters(&path, types_filter, exclude_types) {
+            continue;
+        }
+        if unignored {
+            collected_paths.extend(
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|dir| *dir != root)
+                    .map(|dir| ScannedEntry::directory(dir.to_path_buf())),
+            );
+        }
+        collected_paths.push(ScannedEntry {
+            path,
+            is_dir,
+            size,
+            modified,
+            is_symlink,
+        });
+    }
+    if progress_shown {
+        eprint!("{}", CLEAR_LINE);
+    }
 
-        let is_dir = path.is_dir();
-
-        if !types_filter.is_empty() && !is_dir {
-            // Apply type filter only to files
-            let keep = types_filter
-                .iter()
-                .any(|ext_filter_str| {
-                    let file_name = path.file_name()
-                        .and_then(|name| name.to_str())
-                        .unwrap_or("");
-                    let ext_with_dot = format!(".{}", ext_filter_str);
-                    file_name.ends_with(&ext_with_dot)
-                });
-            if !keep {
-                continue;
+    collected_paths.sort_by(|a, b| a.path.cmp(&b.path));
+    collected_paths.dedup_by(|a, b| a.path == b.path); // Deduplicate, root might be added twice
+
+    Ok((collected_paths, warnings))
+}
+
+// The directories, relative to the root, that every file an --include-ignored-globs glob can
+// match lies below: the literal leading directories of each glob (`build` for
+// `build/**/*.txt`). None when a glob can match anywhere, like `*.log` or `**/tmp/*`.
+fn glob_leading_dirs(globs: &[String]) -> Option<Vec<PathBuf>> {
+    globs
+        .iter()
+        .map(|glob| {
+            let glob = glob.trim_end_matches('/');
+            // As in .gitignore, only a glob with a slash before its end is anchored at the root.
+            if glob.starts_with('!') || !glob.contains('/') {
+                return None;
             }
+            let mut parts: Vec<&str> = glob.trim_start_matches('/').split('/').collect();
+            parts.pop(); // The entry itself, not a directory above it.
+            let leading_dir: PathBuf = parts
+                .into_iter()
+                .take_while(|part| !part.contains(['*', '?', '[', '{', '\\']))
+                .collect();
+            (!leading_dir.as_os_str().is_empty()).then_some(leading_dir)
+        })
+        .collect()
+}
+
+// A walker over `root` with the visibility, exclude, symlink and depth settings of `options`.
+// `ignore_rules_off` disables the .gitignore/.ignore rules. With `leading_dirs`, only entries
+// on the way to or below one of those directories (relative to the root) are visited.
+fn configured_walker(
+    root: &Path,
+    options: &ScanOptions,
+    ignore_rules_off: bool,
+    leading_dirs: Option<Vec<PathBuf>>,
+) -> WalkBuilder {
+    let mut walker = WalkBuilder::new(root);
+
+    if ignore_rules_off {
+        walker.git_ignore(false).ignore(false);
+    }
+    walker.hidden(!options.hidden);
+    let skip_git = options.hidden;
+    let excludes = options.excludes.clone();
+    let walk_root = root.to_path_buf();
+    walker.filter_entry(move |entry| {
+        // .git is hidden but never ignored, so it would otherwise flood the listing.
+        if skip_git && entry.file_name() == ".git" {
+            return false;
         }
-        collected_paths.push((path, is_dir));
+        // Excludes are matched against the path below the root, never the root itself.
+        let relative = entry
+            .path()
+            .strip_prefix(&walk_root)
+            .unwrap_or(entry.path());
+        let on_the_way = |dirs: &Vec<PathBuf>| {
+            dirs.iter()
+                .any(|dir| dir.starts_with(relative) || relative.starts_with(dir))
+        };
+        entry.depth() == 0
+            || (leading_dirs.as_ref().is_none_or(on_the_way)
+                && !excludes.iter().any(|p| p.matches_path(relative)))
+    });
+    // The walker detects symlink cycles itself and reports them as errors, which are
+    // surfaced as warnings instead of looping forever.
+    walker.follow_links(options.follow_symlinks);
+    // Depth 1 is the root's immediate children. This bounds the walk itself, so it applies
+    // before any glob pattern is matched.
+    walker.max_depth(options.max_depth);
+    walker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // Creates `files` (with parent directories) under a fresh temporary directory.
+    fn tree(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        dir
+    }
+
+    // Paths of the walked entries relative to `root`, the root itself left out.
+    fn relative_paths(root: &Path, entries: &[ScannedEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .filter(|entry| entry.path != root)
+            .map(|entry| {
+                entry
+                    .path
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symlink_loop_ends_the_walk_with_a_warning() {
+        let dir = tree(&["a.txt"]);
+        std::os::unix::fs::symlink(".", dir.path().join("link")).unwrap();
+        let options = ScanOptions {
+            follow_symlinks: true,
+            ..ScanOptions::default()
+        };
+        let (entries, warnings) = walk(dir.path(), &[], &[], &options).unwrap();
+        assert_eq!(relative_paths(dir.path(), &entries), vec!["a.txt"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].to_string().contains("File system loop found"),
+            "{}",
+            warnings[0]
+        );
     }
 
-    collected_paths.sort_by(|(a, _), (b, _)| a.cmp(b));
-    collected_paths.dedup_by(|(a, _), (b, _)| a == b); // Deduplicate, root might be added twice
+    #[test]
+    fn depth_one_is_the_roots_immediate_children() {
+        let dir = tree(&["a.txt", "sub/b.txt", "sub/deeper/c.txt"]);
+        let walk_to = |max_depth| {
+            let options = ScanOptions {
+                max_depth,
+                ..ScanOptions::default()
+            };
+            let (entries, _) = walk(dir.path(), &[], &[], &options).unwrap();
+            relative_paths(dir.path(), &entries)
+        };
+        assert_eq!(walk_to(Some(1)), vec!["a.txt", "sub"]);
+        assert_eq!(
+            walk_to(Some(2)),
+            vec!["a.txt", "sub", "sub/b.txt", "sub/deeper"]
+        );
+        assert_eq!(
+            walk_to(None),
+            vec![
+                "a.txt",
+                "sub",
+                "sub/b.txt",
+                "sub/deeper",
+                "sub/deeper/c.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn exclude_type_removes_from_what_type_kept() {
+        let strings =
+            |items: &[&str]| -> Vec<String> { items.iter().map(|s| s.to_string()).collect() };
+        let matches = |path: &str, types: &[&str], excludes: &[&str]| {
+            matches_type_filters(Path::new(path), &strings(types), &strings(excludes))
+        };
+        assert!(matches("src/a.rs", &["rs", "toml"], &["toml"]));
+        assert!(!matches("Cargo.toml", &["rs", "toml"], &["toml"]));
+        assert!(!matches("notes.txt", &["rs", "toml"], &["toml"]));
+        // A compound extension can be excluded from a shorter one.
+        assert!(matches("data.gz", &["gz"], &["tar.gz"]));
+        assert!(!matches("data.tar.gz", &["gz"], &["tar.gz"]));
+        // Without --type, everything but the excluded types passes.
+        assert!(matches("src/a.rs", &[], &["lock"]));
+        assert!(!matches("Cargo.lock", &[], &["lock"]));
+    }
+
+    #[test]
+    fn dotfiles_are_only_walked_with_hidden() {
+        let dir = tree(&[".hidden", ".config/app.toml", ".git/HEAD", "visible.txt"]);
+        let walk_hidden = |hidden| {
+            let options = ScanOptions {
+                hidden,
+                ..ScanOptions::default()
+            };
+            let (entries, _) = walk(dir.path(), &[], &[], &options).unwrap();
+            relative_paths(dir.path(), &entries)
+        };
+        assert_eq!(walk_hidden(false), vec!["visible.txt"]);
+        // .git stays out even then.
+        assert_eq!(
+            walk_hidden(true),
+            vec![".config", ".config/app.toml", ".hidden", "visible.txt"]
+        );
+    }
+
+    #[test]
+    fn type_filters_ignore_the_case_of_extensions() {
+        let dir = tree(&["FOO.RS", "bar.rs", "baz.txt", "archive.TAR.GZ"]);
+        let walk_types = |types: &[&str]| {
+            let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+            let (entries, _) = walk(dir.path(), &types, &[], &ScanOptions::default()).unwrap();
+            relative_paths(dir.path(), &entries)
+        };
+        assert_eq!(walk_types(&["rs"]), vec!["FOO.RS", "bar.rs"]);
+        assert_eq!(walk_types(&["RS"]), vec!["FOO.RS", "bar.rs"]);
+        assert_eq!(walk_types(&["tar.gz"]), vec!["archive.TAR.GZ"]);
+    }
 
-    Ok(collected_paths)
+    #[test]
+    fn default_excludes_prune_target_at_any_depth() {
+        let dir = tree(&[
+            "src/main.rs",
+            "target/debug/out.rs",
+            "crates/foo/target/x.rs",
+        ]);
+        let walk_with = |no_defaults| {
+            let options = ScanOptions {
+                excludes: exclude_patterns(&[], no_defaults).unwrap(),
+                ..ScanOptions::default()
+            };
+            let (entries, _) = walk(dir.path(), &[], &[], &options).unwrap();
+            relative_paths(dir.path(), &entries)
+        };
+        assert_eq!(
+            walk_with(false),
+            vec!["crates", "crates/foo", "src", "src/main.rs"]
+        );
+        assert!(walk_with(true).contains(&"target/debug/out.rs".to_string()));
+    }
+
+    #[test]
+    fn only_anchored_globs_narrow_the_unignored_walk() {
+        let globs =
+            |globs: &[&str]| -> Vec<String> { globs.iter().map(|g| g.to_string()).collect() };
+        assert_eq!(
+            glob_leading_dirs(&globs(&["build/keep.txt", "/dist/**/*.map", "a/b/*/c"])),
+            Some(vec![
+                PathBuf::from("build"),
+                PathBuf::from("dist"),
+                PathBuf::from("a/b")
+            ])
+        );
+        assert_eq!(
+            glob_leading_dirs(&globs(&["build/keep.txt", "*.log"])),
+            None
+        );
+        assert_eq!(glob_leading_dirs(&globs(&["logs/"])), None);
+        assert_eq!(glob_leading_dirs(&globs(&["**/tmp/x"])), None);
+        assert_eq!(glob_leading_dirs(&globs(&["/top.txt"])), None);
+    }
+
+    #[test]
+    fn an_unanchored_include_ignored_glob_matches_at_any_depth() {
+        let dir = tree(&[
+            "src/main.rs",
+            "logs/app.log",
+            "src/debug.log",
+            "build/out.txt",
+        ]);
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        let options = ScanOptions {
+            include_ignored_globs: vec!["*.log".to_string()],
+            ..ScanOptions::default()
+        };
+        let (entries, _) = walk(dir.path(), &[], &[], &options).unwrap();
+        assert_eq!(
+            relative_paths(dir.path(), &entries),
+            vec![
+                "logs",
+                "logs/app.log",
+                "src",
+                "src/debug.log",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn include_ignored_globs_let_only_matching_files_through() {
+        let dir = tree(&[
+            "src/main.rs",
+            "build/keep.txt",
+            "build/drop.txt",
+            "build/nested/drop.txt",
+            "logs/app.log",
+        ]);
+        // The ignore crate only applies .gitignore inside a git repository.
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "build/\nlogs/\n").unwrap();
+        let options = ScanOptions {
+            include_ignored_globs: vec!["build/keep.txt".to_string()],
+            ..ScanOptions::default()
+        };
+        let (entries, warnings) = walk(dir.path(), &[], &[], &options).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            relative_paths(dir.path(), &entries),
+            vec!["build", "build/keep.txt", "src", "src/main.rs"]
+        );
+        let build = entries.iter().find(|e| e.path.ends_with("build")).unwrap();
+        assert!(build.is_dir);
+    }
 }
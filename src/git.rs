@@ -0,0 +1,110 @@
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Could not run git; is it installed and on PATH?")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("not a git repository") {
+            return Err(anyhow!("{} is not inside a git repository", dir.display()));
+        }
+        return Err(anyhow!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Path of `root` below the repository top level, e.g. `sub/dir/` (empty at the top).
+/// Git reports paths relative to the top level; stripping this maps them onto `root`.
+fn root_prefix(root: &Path) -> Result<String> {
+    let prefix = run_git(root, &["rev-parse", "--show-prefix"])?;
+    Ok(prefix.trim_end_matches('\n').to_string())
+}
+
+/// Files under `root` with uncommitted changes: modified, added, renamed or untracked
+/// (deleted files are left out). Paths are `root` joined with the path below it, matching
+/// what the directory walk produces.
+pub fn modified_files(root: &Path) -> Result<HashSet<PathBuf>> {
+    let prefix = root_prefix(root)?;
+    let status = run_git(
+        root,
+        &["status", "--porcelain", "-z", "--untracked-files=all", "."],
+    )?;
+
+    let mut files = HashSet::new();
+    let mut entries = status.split('\0');
+    while let Some(entry) = entries.next() {
+        // Each entry is `XY <path>`; renames and copies are followed by the original path.
+        let Some((code, path)) = entry.get(..2).zip(entry.get(3..)) else {
+            continue;
+        };
+        if code.starts_with(['R', 'C']) {
+            entries.next();
+        }
+        if code.contains('D') {
+            continue;
+        }
+        if let Some(below_root) = path.strip_prefix(&prefix) {
+            files.insert(root.join(below_root));
+        }
+    }
+    Ok(files)
+}
+
+/// Files under `root` that differ between `reference` and the working tree, as listed by
+/// `git diff --name-only` (untracked files are not included). Deleted files are left out.
+pub fn files_changed_since(root: &Path, reference: &str) -> Result<HashSet<PathBuf>> {
+    // Resolving the prefix first makes "not a git repository" win over "unknown ref".
+    let prefix = root_prefix(root)?;
+    let commit = format!("{}^{{commit}}", reference);
+    if run_git(root, &["rev-parse", "--verify", "--quiet", &commit]).is_err() {
+        return Err(anyhow!(
+            "--git-diff: '{}' is not a known git ref",
+            reference
+        ));
+    }
+    diff_names(root, &prefix, &[reference])
+}
+
+/// Files under `root` staged in the index (`git diff --cached --name-only`), except deletions.
+pub fn staged_files(root: &Path) -> Result<HashSet<PathBuf>> {
+    let prefix = root_prefix(root)?;
+    diff_names(root, &prefix, &["--cached"])
+}
+
+// Runs `git diff --name-only` with `diff_args`, limited to `root` and without deletions.
+fn diff_names(root: &Path, prefix: &str, diff_args: &[&str]) -> Result<HashSet<PathBuf>> {
+    let mut args = vec!["diff", "--name-only", "-z", "--diff-filter=d"];
+    args.extend_from_slice(diff_args);
+    args.extend_from_slice(&["--", "."]);
+    let diff = run_git(root, &args)?;
+    Ok(diff
+        .split('\0')
+        .filter_map(|path| path.strip_prefix(prefix))
+        .filter(|below_root| !below_root.is_empty())
+        .map(|below_root| root.join(below_root))
+        .collect())
+}
+
+/// Whether `dir` is inside a git working tree.
+pub fn is_inside_work_tree(dir: &Path) -> bool {
+    run_git(dir, &["rev-parse", "--is-inside-work-tree"]).is_ok_and(|out| out.trim() == "true")
+}
+
+/// The last commit touching `file`, as `<short-sha> <date> by <author>`. None when the file
+/// has never been committed or git cannot tell.
+pub fn last_commit_summary(file: &Path) -> Option<String> {
+    let dir = file
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = file.file_name()?.to_str()?;
+    let summary = run_git(dir, &["log", "-1", "--format=%h %as by %an", "--", name]).ok()?;
+    let summary = summary.trim();
+    (!summary.is_empty()).then(|| summary.to_string())
+}
@@ -1,13 +1,22 @@
 mod cli;
 mod clipboard;
+mod comment_stripper;
+mod config;
+mod file_directives;
 mod file_scanner;
+mod git;
+mod redact;
+mod selection_store;
+#[cfg(feature = "send")]
+mod send;
+mod template;
 mod tree_builder;
 mod tui;
 mod utils;
 mod workflow;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 
 fn main() -> Result<()> {
     // Handle daemon mode first. This should stay in main.rs as it's an early exit.
@@ -15,8 +24,17 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let cli_args = cli::Cli::parse();
+    // Keep the raw matches so config defaults only fill options not given on the command line.
+    let matches = cli::Cli::command().get_matches();
+    let cli_args = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     // Delegate the main application logic to the workflow module
-    workflow::run_repoyank(cli_args)
+    let result = workflow::run_repoyank(cli_args, &matches);
+    // These outcomes were already reported; only the exit status is left to set.
+    if let Err(e) = &result
+        && let Some(exit) = e.downcast_ref::<workflow::Exit>()
+    {
+        std::process::exit(exit.code());
+    }
+    result
 }
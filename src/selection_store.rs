@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LAST_SELECTIONS_FILE: &str = "last_selections.json";
+
+/// `$XDG_STATE_HOME/repoyank/last_selections.json`, falling back to `~/.local/state`. Kept
+/// out of the repository so saving a selection never adds a file to the user's tree.
+fn store_path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(state_home.join("repoyank").join(LAST_SELECTIONS_FILE))
+}
+
+// Every saved selection, keyed by the canonical scan root.
+type Selections = BTreeMap<String, Vec<String>>;
+
+fn read_selections(path: &Path) -> Result<Selections> {
+    if !path.exists() {
+        return Ok(Selections::new());
+    }
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))
+}
+
+fn root_key(scan_root: &Path) -> String {
+    scan_root
+        .canonicalize()
+        .unwrap_or_else(|_| scan_root.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Saves the yanked files, relative to `scan_root`, as the last selection for that root.
+pub fn save_last_selection(scan_root: &Path, files: &[PathBuf]) -> Result<()> {
+    let store_path = store_path().context("Neither XDG_STATE_HOME nor HOME is set")?;
+    let relative_paths: Vec<String> = files
+        .iter()
+        .map(|path| {
+            path.strip_prefix(scan_root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    // A corrupt store is replaced rather than blocking every later save.
+    let mut selections = read_selections(&store_path).unwrap_or_default();
+    selections.insert(root_key(scan_root), relative_paths);
+    if let Some(parent) = store_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&store_path, serde_json::to_string_pretty(&selections)?)?;
+    Ok(())
+}
+
+/// Loads the previously saved selection as paths under `scan_root`.
+/// Returns an empty list if nothing has been saved for it yet.
+pub fn load_last_selection(scan_root: &Path) -> Result<Vec<PathBuf>> {
+    let Some(store_path) = store_path() else {
+        return Ok(Vec::new());
+    };
+    let mut selections = read_selections(&store_path)?;
+    Ok(selections
+        .remove(&root_key(scan_root))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|relative| scan_root.join(relative))
+        .collect())
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+
+/// LLM API flavour used by `--send`. Endpoint, key and model come from the environment.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provider {
+    /// OpenAI-compatible chat completions (`OPENAI_API_KEY`, `OPENAI_BASE_URL`, `OPENAI_MODEL`).
+    Openai,
+    /// Anthropic messages API (`ANTHROPIC_API_KEY`, `ANTHROPIC_BASE_URL`, `ANTHROPIC_MODEL`).
+    Anthropic,
+}
+
+impl Provider {
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Provider::Openai => "OpenAI",
+            Provider::Anthropic => "Anthropic",
+        }
+    }
+}
+
+const OPENAI_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const OPENAI_DEFAULT_MODEL: &str = "gpt-4o-mini";
+const ANTHROPIC_DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_DEFAULT_MODEL: &str = "claude-3-5-haiku-latest";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const ANTHROPIC_MAX_TOKENS: u32 = 4096;
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+fn required_env(name: &str) -> Result<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| anyhow!("{} is not set; it is required for --send", name))
+}
+
+/// Sends `prompt` followed by the yanked `context` to `provider` and streams the reply
+/// to stdout as it arrives. This makes a network request.
+pub fn send_and_stream(provider: Provider, prompt: &str, context: &str) -> Result<()> {
+    let message = format!("{}\n\n{}", prompt, context);
+    let request = match provider {
+        Provider::Openai => {
+            let api_key = required_env("OPENAI_API_KEY")?;
+            let base_url = env_or("OPENAI_BASE_URL", OPENAI_DEFAULT_BASE_URL);
+            let body = json!({
+                "model": env_or("OPENAI_MODEL", OPENAI_DEFAULT_MODEL),
+                "stream": true,
+                "messages": [{ "role": "user", "content": message }],
+            });
+            ureq::post(&format!(
+                "{}/chat/completions",
+                base_url.trim_end_matches('/')
+            ))
+            .set("Authorization", &format!("Bearer {}", api_key))
+            .send_json(body)
+        }
+        Provider::Anthropic => {
+            let api_key = required_env("ANTHROPIC_API_KEY")?;
+            let base_url = env_or("ANTHROPIC_BASE_URL", ANTHROPIC_DEFAULT_BASE_URL);
+            let body = json!({
+                "model": env_or("ANTHROPIC_MODEL", ANTHROPIC_DEFAULT_MODEL),
+                "max_tokens": ANTHROPIC_MAX_TOKENS,
+                "stream": true,
+                "messages": [{ "role": "user", "content": message }],
+            });
+            ureq::post(&format!("{}/v1/messages", base_url.trim_end_matches('/')))
+                .set("x-api-key", &api_key)
+                .set("anthropic-version", ANTHROPIC_VERSION)
+                .send_json(body)
+        }
+    };
+    let response = match request {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            return Err(anyhow!("API request failed with status {}: {}", code, body));
+        }
+        Err(e) => return Err(e).context("Could not reach the API"),
+    };
+
+    // Both APIs stream server-sent events: `data: {json}` lines, ending with `[DONE]` for OpenAI.
+    let mut stdout = std::io::stdout();
+    for line in BufReader::new(response.into_reader()).lines() {
+        let line = line.context("Reading the API response stream failed")?;
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            continue;
+        };
+        if data == "[DONE]" {
+            break;
+        }
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            continue;
+        };
+        if let Some(error) = event.get("error") {
+            return Err(anyhow!("API returned an error: {}", error));
+        }
+        let text = match provider {
+            Provider::Openai => event["choices"][0]["delta"]["content"].as_str(),
+            Provider::Anthropic => event["delta"]["text"].as_str(),
+        };
+        if let Some(text) = text {
+            stdout.write_all(text.as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+    writeln!(stdout)?;
+    Ok(())
+}
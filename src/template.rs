@@ -0,0 +1,246 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs;
+use std::path::Path;
+
+/// A `{{name}}` placeholder. `tree` belongs to the header and footer; the others to the
+/// per-file section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    /// The file's path, as it would appear in a `File:` header.
+    Path,
+    /// The file's contents, trailing whitespace trimmed.
+    Content,
+    /// The lower-case extension, e.g. `rs`, usable as a code fence language; empty when the
+    /// file has none.
+    Lang,
+    /// The file's 1-based position in the output.
+    Index,
+    /// The directory tree; empty with `--no-tree`.
+    Tree,
+}
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Placeholder::Path),
+            "content" => Some(Placeholder::Content),
+            "lang" => Some(Placeholder::Lang),
+            "index" => Some(Placeholder::Index),
+            "tree" => Some(Placeholder::Tree),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Placeholder::Path => "path",
+            Placeholder::Content => "content",
+            Placeholder::Lang => "lang",
+            Placeholder::Index => "index",
+            Placeholder::Tree => "tree",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Section {
+    Header,
+    File,
+    Footer,
+}
+
+impl Section {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "header" => Some(Section::Header),
+            "file" => Some(Section::File),
+            "footer" => Some(Section::Footer),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Section::Header => "header",
+            Section::File => "file",
+            Section::Footer => "footer",
+        }
+    }
+
+    fn allows(self, placeholder: Placeholder) -> bool {
+        (self == Section::File) != (placeholder == Placeholder::Tree)
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Values substituted into the per-file section for one file.
+pub struct FileValues<'a> {
+    pub path: &'a str,
+    pub content: &'a str,
+    pub lang: &'a str,
+    pub index: usize,
+}
+
+/// A `--template` file: an optional `{{#header}}…{{/header}}` rendered once before the
+/// files, a `{{#file}}…{{/file}}` section rendered per file, and an optional
+/// `{{#footer}}…{{/footer}}` rendered once after them. A template without section tags is
+/// all per-file section. The line break right after an opening tag is dropped, so tags can
+/// sit on lines of their own.
+#[derive(Debug, Default)]
+pub struct Template {
+    header: Vec<Segment>,
+    file: Vec<Segment>,
+    footer: Vec<Segment>,
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Could not read --template file {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid --template file {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let mut template = Template::default();
+        let mut outside: Vec<Segment> = Vec::new();
+        let mut seen_sections: Vec<Section> = Vec::new();
+        let mut current: Option<Section> = None;
+        let mut rest = text;
+
+        loop {
+            let (before, tag, after) = match rest.find("{{") {
+                Some(start) => {
+                    let end = rest[start..]
+                        .find("}}")
+                        .map(|end| start + end)
+                        .ok_or_else(|| {
+                            anyhow!("unclosed '{{{{' in '{}'", snippet(&rest[start..]))
+                        })?;
+                    (
+                        &rest[..start],
+                        Some(rest[start + 2..end].trim()),
+                        &rest[end + 2..],
+                    )
+                }
+                None => (rest, None, ""),
+            };
+            let segments = match current {
+                Some(section) => template.section_mut(section),
+                None => &mut outside,
+            };
+            if !before.is_empty() {
+                segments.push(Segment::Text(before.to_string()));
+            }
+            let Some(tag) = tag else {
+                break;
+            };
+            rest = after;
+
+            if let Some(name) = tag.strip_prefix('#') {
+                let section = Section::from_name(name)
+                    .ok_or_else(|| anyhow!("unknown section {{{{#{}}}}}", name))?;
+                if let Some(open) = current {
+                    bail!("{{{{#{}}}}} inside the {} section", name, open.name());
+                }
+                if seen_sections.contains(&section) {
+                    bail!("the {{{{#{}}}}} section appears twice", name);
+                }
+                seen_sections.push(section);
+                current = Some(section);
+                rest = rest
+                    .strip_prefix("\r\n")
+                    .or_else(|| rest.strip_prefix('\n'))
+                    .unwrap_or(rest);
+            } else if let Some(name) = tag.strip_prefix('/') {
+                if current.is_none() || current != Section::from_name(name) {
+                    bail!("{{{{/{}}}}} does not close an open section", name);
+                }
+                current = None;
+            } else {
+                let placeholder = Placeholder::from_name(tag).ok_or_else(|| {
+                    anyhow!(
+                        "unknown placeholder {{{{{}}}}}; the file section supports {{{{path}}}}, \
+                         {{{{content}}}}, {{{{lang}}}} and {{{{index}}}}, the header and footer \
+                         {{{{tree}}}}",
+                        tag
+                    )
+                })?;
+                segments.push(Segment::Placeholder(placeholder));
+            }
+        }
+
+        if let Some(open) = current {
+            bail!("the {} section is never closed", open.name());
+        }
+        if seen_sections.is_empty() {
+            template.file = outside;
+        } else if outside.iter().any(|segment| match segment {
+            Segment::Text(text) => !text.trim().is_empty(),
+            Segment::Placeholder(_) => true,
+        }) {
+            bail!("text outside the {{{{#header}}}}, {{{{#file}}}} and {{{{#footer}}}} sections");
+        }
+
+        for section in [Section::Header, Section::File, Section::Footer] {
+            for segment in template.section_mut(section).iter() {
+                if let Segment::Placeholder(placeholder) = segment
+                    && !section.allows(*placeholder)
+                {
+                    bail!(
+                        "{{{{{}}}}} cannot be used in the {} section",
+                        placeholder.name(),
+                        section.name()
+                    );
+                }
+            }
+        }
+        Ok(template)
+    }
+
+    fn section_mut(&mut self, section: Section) -> &mut Vec<Segment> {
+        match section {
+            Section::Header => &mut self.header,
+            Section::File => &mut self.file,
+            Section::Footer => &mut self.footer,
+        }
+    }
+
+    pub fn render_header(&self, tree: &str) -> String {
+        render(&self.header, |_| tree.to_string())
+    }
+
+    pub fn render_file(&self, values: &FileValues) -> String {
+        render(&self.file, |placeholder| match placeholder {
+            Placeholder::Path => values.path.to_string(),
+            Placeholder::Content => values.content.to_string(),
+            Placeholder::Lang => values.lang.to_string(),
+            Placeholder::Index => values.index.to_string(),
+            Placeholder::Tree => unreachable!("rejected when parsing"),
+        })
+    }
+
+    pub fn render_footer(&self, tree: &str) -> String {
+        render(&self.footer, |_| tree.to_string())
+    }
+}
+
+fn render(segments: &[Segment], value: impl Fn(Placeholder) -> String) -> String {
+    let mut rendered = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => rendered.push_str(text),
+            Segment::Placeholder(placeholder) => rendered.push_str(&value(*placeholder)),
+        }
+    }
+    rendered
+}
+
+// The start of `text`, for pointing at a parse error.
+fn snippet(text: &str) -> String {
+    text.chars().take(20).collect()
+}
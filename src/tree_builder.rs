@@ -1,11 +1,67 @@
+use crate::cli::SortOrder;
+use crate::file_scanner::ScannedEntry;
+use crate::utils;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::{Component, Path, PathBuf};
 
+/// Sort entries into tree order: every directory directly followed by its descendants, with
+/// siblings ordered by `order`.
+///
+/// `SortOrder::Name` is a plain path sort, unless `dirs_first`. The other orders, and
+/// `Name` with `dirs_first`, list subdirectories first (by name) and then files by the sort
+/// key, breaking ties by name. Sizes and times come from the entries; nothing is statted.
+pub fn sort_tree_entries(entries: &mut [ScannedEntry], order: SortOrder, dirs_first: bool) {
+    if order == SortOrder::Name && !dirs_first {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        return;
+    }
+    entries.sort_by(|a, b| compare_in_tree(a, b, order));
+}
+
+fn compare_in_tree(a_entry: &ScannedEntry, b_entry: &ScannedEntry, order: SortOrder) -> Ordering {
+    let (a, a_is_dir) = (a_entry.path.as_path(), a_entry.is_dir);
+    let (b, b_is_dir) = (b_entry.path.as_path(), b_entry.is_dir);
+    let mut a_components = a.components();
+    let mut b_components = b.components();
+    loop {
+        match (a_components.next(), b_components.next()) {
+            (None, None) => return Ordering::Equal,
+            // An ancestor always comes before its descendants.
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_part), Some(b_part)) if a_part == b_part => {}
+            (Some(a_part), Some(b_part)) => {
+                // The first differing components are siblings; one with components left
+                // after it is an intermediate directory.
+                let a_sibling_is_dir = a_is_dir || a_components.next().is_some();
+                let b_sibling_is_dir = b_is_dir || b_components.next().is_some();
+                let by_name = a_part.cmp(&b_part);
+                return match (a_sibling_is_dir, b_sibling_is_dir) {
+                    (true, true) => by_name,
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (false, false) => {
+                        let by_key = match order {
+                            SortOrder::Name => Ordering::Equal,
+                            SortOrder::Size => b_entry.size.cmp(&a_entry.size),
+                            SortOrder::Mtime => b_entry.modified.cmp(&a_entry.modified),
+                            SortOrder::Ext => utils::extension_key(a).cmp(&utils::extension_key(b)),
+                        };
+                        by_key.then(by_name)
+                    }
+                };
+            }
+        }
+    }
+}
+
 /// Build pretty tree-style labels in **O(n)**.
 ///
-/// * `paths` **must** be lexicographically sorted.
+/// * `paths` **must** be in tree order: sorted by path, or by [`sort_tree_entries`].
 /// * Each element in `paths` is `(path, is_dir)`.
-pub fn build_tree_labels(paths: &[(PathBuf, bool)], root_path: &Path) -> Vec<String> {
+/// * With `icons`, every entry below the root gets its [`utils::file_icon`] before the name.
+pub fn build_tree_labels(paths: &[(PathBuf, bool)], root_path: &Path, icons: bool) -> Vec<String> {
     let n = paths.len();
     let mut labels = Vec::with_capacity(n);
     // is_last_for_ancestor_at_depth[d] is true if the ancestor at depth 'd' is the last child of *its* parent.
@@ -65,15 +121,15 @@ pub fn build_tree_labels(paths: &[(PathBuf, bool)], root_path: &Path) -> Vec<Str
 
         let name = rel
             .file_name()
-            .unwrap_or_else(|| {
-                std::ffi::OsStr::new(if rel.as_os_str().is_empty() || rel == Path::new(".") {
-                    "."
-                } else {
-                    "." /* should not happen if path is not root */
-                })
-            })
+            .unwrap_or_else(|| std::ffi::OsStr::new(".")) // Only the root has no file name
             .to_string_lossy();
 
+        if icons {
+            // After the branch glyphs, so the alignment of nested entries is untouched.
+            prefix.push_str(utils::file_icon(path, *is_dir));
+            prefix.push(' ');
+        }
+
         let label = if path == root_path || (rel.as_os_str().is_empty() || rel == Path::new(".")) {
             "./".to_string()
         } else if *is_dir {
@@ -105,3 +161,216 @@ pub fn build_tree_labels(paths: &[(PathBuf, bool)], root_path: &Path) -> Vec<Str
     }
     labels
 }
+
+/// Collapse runs of directories that each hold exactly one entry, itself a directory, into a
+/// single `src/main/java/` line (`--compact-tree`).
+///
+/// * `labels` must be the output of [`build_tree_labels`] for `paths`.
+/// * Returns the remaining labels with, for each, the path it stands for: the deepest
+///   directory of a collapsed run, whose descendants are drawn one level shallower.
+pub fn compact_tree_labels(
+    labels: Vec<String>,
+    paths: &[(PathBuf, bool)],
+    root_path: &Path,
+) -> (Vec<String>, Vec<(PathBuf, bool)>) {
+    let mut child_counts = HashMap::<&Path, usize>::new();
+    for (path, _) in paths {
+        if let Some(parent) = path.parent() {
+            *child_counts.entry(parent).or_insert(0) += 1;
+        }
+    }
+    let depth_of = |path: &Path| {
+        let rel = path.strip_prefix(root_path).unwrap_or(path);
+        rel.components().filter(|c| *c != Component::CurDir).count()
+    };
+
+    let mut compact_labels: Vec<String> = Vec::with_capacity(labels.len());
+    let mut compact_paths: Vec<(PathBuf, bool)> = Vec::with_capacity(paths.len());
+    // Directories folded into their parent's line that are ancestors of the current entry,
+    // with their depth; each one's column is dropped from its descendants' prefixes.
+    let mut folded: Vec<(&Path, usize)> = Vec::new();
+    for (idx, (label, (path, is_dir))) in labels.into_iter().zip(paths).enumerate() {
+        while folded
+            .last()
+            .is_some_and(|(ancestor, _)| !path.starts_with(ancestor))
+        {
+            folded.pop();
+        }
+
+        // In tree order an only child directly follows its parent.
+        let only_child_dir_of_previous = idx > 0 && *is_dir && {
+            let (parent, parent_is_dir) = &paths[idx - 1];
+            *parent_is_dir
+                && parent != root_path
+                && path.parent() == Some(parent.as_path())
+                && child_counts.get(parent.as_path()) == Some(&1)
+        };
+        if only_child_dir_of_previous && let Some(line) = compact_labels.last_mut() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            line.push_str(&format!("{}/", name));
+            *compact_paths.last_mut().expect("one path per label") = (path.clone(), *is_dir);
+            folded.push((path, depth_of(path)));
+            continue;
+        }
+
+        // Each depth below the root owns one three-character column of the prefix; drop the
+        // deepest folded columns first so the shallower offsets stay valid.
+        let mut chars: Vec<char> = label.chars().collect();
+        for &(_, depth) in folded.iter().rev() {
+            let column = 3 * (depth - 1);
+            if chars.len() >= column + 3 {
+                chars.drain(column..column + 3);
+            }
+        }
+        compact_labels.push(chars.into_iter().collect());
+        compact_paths.push((path.clone(), *is_dir));
+    }
+    (compact_labels, compact_paths)
+}
+
+/// Append `(≈ N tokens)` to the labels produced by [`build_tree_labels`].
+///
+/// * `paths` must be the same slice the labels were built from (or the paths returned by
+///   [`compact_tree_labels`]).
+/// * Files use their own count from `token_counts`; directories show the sum of their
+///   descendants. Paths with no counted descendants are left untouched.
+pub fn annotate_tree_labels_with_tokens(
+    labels: &mut [String],
+    paths: &[(PathBuf, bool)],
+    token_counts: &HashMap<PathBuf, usize>,
+) {
+    let mut totals = HashMap::<&Path, usize>::new();
+    for (path, tokens) in token_counts {
+        // Credit the file itself and every ancestor that appears in the tree.
+        for ancestor in path.ancestors() {
+            *totals.entry(ancestor).or_insert(0) += tokens;
+        }
+    }
+    for (label, (path, _)) in labels.iter_mut().zip(paths) {
+        if let Some(tokens) = totals.get(path.as_path()) {
+            label.push_str(&format!(
+                " (≈ {} tokens)",
+                utils::format_token_count(*tokens)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `(path, is_dir)` pairs under `root`, in the order given.
+    fn nodes(root: &str, entries: &[(&str, bool)]) -> Vec<(PathBuf, bool)> {
+        entries
+            .iter()
+            .map(|(path, is_dir)| (Path::new(root).join(path), *is_dir))
+            .collect()
+    }
+
+    fn entry(path: &str, is_dir: bool, size: u64) -> ScannedEntry {
+        ScannedEntry {
+            path: PathBuf::from(path),
+            is_dir,
+            size,
+            modified: None,
+            is_symlink: false,
+        }
+    }
+
+    fn sorted_paths(entries: &mut [ScannedEntry], order: SortOrder, dirs_first: bool) -> Vec<&str> {
+        sort_tree_entries(entries, order, dirs_first);
+        entries
+            .iter()
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn dirs_first_lists_subdirectories_before_files_at_every_level() {
+        let mut entries = vec![
+            entry("r/b.txt", false, 1),
+            entry("r/z/c.txt", false, 1),
+            entry("r/a.txt", false, 1),
+            entry("r/z", true, 0),
+            entry("r", true, 0),
+            entry("r/z/y", true, 0),
+            entry("r/m", true, 0),
+        ];
+        assert_eq!(
+            sorted_paths(&mut entries, SortOrder::Name, true),
+            vec![
+                "r",
+                "r/m",
+                "r/z",
+                "r/z/y",
+                "r/z/c.txt",
+                "r/a.txt",
+                "r/b.txt"
+            ]
+        );
+        assert_eq!(
+            sorted_paths(&mut entries, SortOrder::Name, false),
+            vec![
+                "r",
+                "r/a.txt",
+                "r/b.txt",
+                "r/m",
+                "r/z",
+                "r/z/c.txt",
+                "r/z/y"
+            ]
+        );
+    }
+
+    #[test]
+    fn other_orders_sort_files_by_key_after_the_directories() {
+        let mut entries = vec![
+            entry("r", true, 0),
+            entry("r/small.txt", false, 1),
+            entry("r/big.txt", false, 100),
+            entry("r/sub", true, 0),
+            entry("r/sub/x.txt", false, 5),
+            entry("r/tie.txt", false, 1),
+        ];
+        assert_eq!(
+            sorted_paths(&mut entries, SortOrder::Size, false),
+            vec![
+                "r",
+                "r/sub",
+                "r/sub/x.txt",
+                "r/big.txt",
+                "r/small.txt",
+                "r/tie.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn a_chain_of_only_children_becomes_one_line() {
+        let root = Path::new("r");
+        let paths = nodes(
+            "r",
+            &[
+                ("", true),
+                ("a", true),
+                ("a/b", true),
+                ("a/b/c", true),
+                ("a/b/c/x.rs", false),
+                ("a/b/c/y.rs", false),
+                ("z.txt", false),
+            ],
+        );
+        let labels = build_tree_labels(&paths, root, false);
+        assert_eq!(labels[3], "│     └─ c/");
+
+        let (labels, compact_paths) = compact_tree_labels(labels, &paths, root);
+        assert_eq!(
+            labels,
+            vec!["./", "├─ a/b/c/", "│  ├─ x.rs", "│  └─ y.rs", "└─ z.txt"]
+        );
+        // The merged line stands for the deepest directory of the chain.
+        assert_eq!(compact_paths[1], (PathBuf::from("r/a/b/c"), true));
+        assert_eq!(compact_paths.len(), labels.len());
+    }
+}
@@ -1,8 +1,27 @@
-use super::app_state::{AppMode, SelectableItem, SelectionState};
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use std::collections::HashMap;
+use super::app_state::{
+    AgeBucket, AgeView, AppMode, FilterMatcher, SelectableItem, SelectionState,
+};
+use super::highlight;
+use super::theme::Palette;
+use crate::cli::Theme;
+use crate::file_scanner::ScannedEntry;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
+const PREVIEW_MAX_LINES: usize = 200;
+const PREVIEW_MAX_BYTES: u64 = 64 * 1024;
+const MOUSE_SCROLL_LINES: isize = 3;
+// Content search skips files above this size so one huge file can't stall the UI.
+const GREP_MAX_FILE_BYTES: u64 = 1024 * 1024;
+// Column layout of a list row inside the border: highlight symbol "❯ ", then the
+// "[+] " expansion and "[x] " selection prefixes, four columns each.
+pub(super) const ROW_HIGHLIGHT_WIDTH: u16 = 2;
+const ROW_PREFIX_WIDTH: u16 = 4;
+
 // --- Propagation Helpers (public to the crate via tui/mod.rs re-export) ---
 pub fn apply_state_and_propagate_down_vec(
     items: &mut [SelectableItem],
@@ -19,11 +38,19 @@ pub fn apply_state_and_propagate_down_vec(
             new_state
         };
     items[item_idx].state = actual_new_state;
-    if items[item_idx].is_dir && actual_new_state != SelectionState::PartiallySelected {
-        let children_indices = items[item_idx].children_indices.clone();
-        for child_idx in children_indices {
-            apply_state_and_propagate_down_vec(items, child_idx, actual_new_state);
+    // A partial state only ever applies to the toggled directory itself.
+    if !items[item_idx].is_dir || actual_new_state == SelectionState::PartiallySelected {
+        return;
+    }
+    // Walk the subtree with an explicit stack: deep or huge directories must not be able
+    // to overflow the call stack.
+    let mut pending: Vec<usize> = items[item_idx].children_indices.clone();
+    while let Some(idx) = pending.pop() {
+        if idx >= items.len() {
+            continue;
         }
+        items[idx].state = actual_new_state;
+        pending.extend_from_slice(&items[idx].children_indices);
     }
 }
 
@@ -83,13 +110,40 @@ pub struct TuiApp {
     pub(super) mode: AppMode,
     pub(super) filter_input: String,
     pub(super) filter_cursor_pos: usize,
+    pub(super) filter_is_regex: bool, // Toggled with Ctrl-R while filtering
+    compiled_filter: Result<FilterMatcher, regex::Error>, // Recompiled by refresh_filter on every change
     pub(super) list_viewport_height: usize,
+    pub(super) list_area: Rect, // Last rendered list block, used to map mouse clicks
+    pub(super) age_view: AgeView,
+    pub(super) show_sizes: bool,        // Toggled with `s`
+    pub(super) subtree_sizes: Vec<u64>, // Per item: its size, or for directories the sum of all descendants
+    pub(super) show_preview: bool,
+    pub(super) palette: Palette,
+    preview_cache: Option<(usize, Vec<Line<'static>>)>, // (item index, preview lines)
+    pub(super) root_path: PathBuf,
+    pub(super) fuzzy_input: String,
+    pub(super) fuzzy_cursor: usize, // Position in the ranked result list
+    pub(super) fuzzy_marked: HashSet<usize>, // Item indices marked with Tab
+    pending_g: bool,                // First `g` of a `gg` jump was pressed
+    pub(super) pending_count: Option<usize>, // Digits typed before `G`, e.g. 42 of `42G`
+    pub(super) grep_input: String,
+    pub(super) grep_results: Option<Vec<(usize, usize)>>, // (item index, matching lines); None until searched
+    pub(super) grep_cursor: usize,
+    pub(super) grep_marked: HashSet<usize>,
+    pub(super) show_help: bool,
+    pub(super) help_scroll: usize, // First keybinding line shown; clamped by ui_renderer
+    pub(super) extension_input: String,
+    pub(super) edit_request: Option<usize>, // File to open in $EDITOR; handled by the main loop
+    pub(super) copy_request: bool, // Copy the selection now (`c`); handled by the main loop
+    pub(super) status_message: Option<String>, // Shown in the status line until the next key
 }
 
 impl TuiApp {
-    pub fn new(items: Vec<SelectableItem>) -> Self {
+    pub fn new(items: Vec<SelectableItem>, root_path: &Path, theme: Theme) -> Self {
+        let subtree_sizes = compute_subtree_sizes(&items);
         TuiApp {
             items,
+            subtree_sizes,
             current_selection_idx: 0,
             scroll_offset: 0,
             quit: false,
@@ -97,10 +151,220 @@ impl TuiApp {
             mode: AppMode::Normal,
             filter_input: String::new(),
             filter_cursor_pos: 0,
+            filter_is_regex: false,
+            compiled_filter: compile_filter("", false),
             list_viewport_height: 0, // Will be updated by ui_renderer
+            list_area: Rect::default(),
+            age_view: AgeView::Off,
+            show_sizes: false,
+            show_preview: false,
+            palette: Palette::for_theme(theme),
+            preview_cache: None,
+            root_path: root_path.to_path_buf(),
+            fuzzy_input: String::new(),
+            fuzzy_cursor: 0,
+            fuzzy_marked: HashSet::new(),
+            pending_g: false,
+            pending_count: None,
+            grep_input: String::new(),
+            grep_results: None,
+            grep_cursor: 0,
+            grep_marked: HashSet::new(),
+            show_help: false,
+            help_scroll: 0,
+            extension_input: String::new(),
+            edit_request: None,
+            copy_request: false,
+            status_message: None,
+        }
+    }
+
+    /// Asks the main loop to open the highlighted file in `$EDITOR`.
+    fn request_edit_of_highlighted(&mut self) {
+        match self.items.get(self.current_selection_idx) {
+            Some(item) if !item.is_dir => self.edit_request = Some(self.current_selection_idx),
+            Some(_) => self.status_message = Some("E opens files, not directories".to_string()),
+            None => {}
+        }
+    }
+
+    /// Picks up changes made to an item's file by an editor: its size, and the cached preview.
+    pub(super) fn refresh_after_edit(&mut self, item_idx: usize) {
+        if let Some(item) = self.items.get_mut(item_idx)
+            && let Ok(metadata) = std::fs::metadata(&item.path)
+        {
+            item.size = metadata.len();
+            item.modified = metadata.modified().ok();
+        }
+        self.subtree_sizes = compute_subtree_sizes(&self.items);
+        self.preview_cache = None;
+    }
+
+    /// Number of fully selected files and their combined size in bytes.
+    pub(super) fn selected_files_summary(&self) -> (usize, u64) {
+        self.items
+            .iter()
+            .filter(|item| !item.is_dir && item.state == SelectionState::FullySelected)
+            .fold((0, 0), |(count, bytes), item| {
+                (count + 1, bytes + item.size)
+            })
+    }
+
+    /// Approximate token change if the highlighted item were toggled: positive when it
+    /// would add files, negative when it would remove them. Directories sum their subtree.
+    pub(super) fn toggle_token_delta(&self) -> Option<i64> {
+        let item = self.items.get(self.current_selection_idx)?;
+        let selecting = item.state != SelectionState::FullySelected;
+        let mut bytes = 0u64;
+        let mut stack = vec![self.current_selection_idx];
+        while let Some(idx) = stack.pop() {
+            let node = &self.items[idx];
+            if node.is_dir {
+                stack.extend(node.children_indices.iter().copied());
+            } else if (node.state == SelectionState::FullySelected) != selecting {
+                // Only files whose state would actually flip count towards the change.
+                bytes += node.size;
+            }
+        }
+        let tokens = (bytes / 4) as i64;
+        Some(if selecting { tokens } else { -tokens })
+    }
+
+    /// Preview lines for the highlighted item, read lazily and cached until the highlight moves.
+    pub(super) fn current_preview_lines(&mut self) -> &[Line<'static>] {
+        let idx = self.current_selection_idx;
+        if self
+            .preview_cache
+            .as_ref()
+            .map(|(cached_idx, _)| *cached_idx)
+            != Some(idx)
+        {
+            let lines = self
+                .items
+                .get(idx)
+                .map(|item| load_preview_lines(item, self.palette.syntax_theme))
+                .unwrap_or_default();
+            self.preview_cache = Some((idx, lines));
+        }
+        self.preview_cache
+            .as_ref()
+            .map(|(_, lines)| lines.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Path of an item relative to the scan root, as shown to the user.
+    pub(super) fn relative_path_of(&self, item_idx: usize) -> String {
+        let path = &self.items[item_idx].path;
+        path.strip_prefix(&self.root_path)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    /// All files ranked by fuzzy score against `fuzzy_input`, best match first.
+    pub(super) fn fuzzy_ranked_file_indices(&self) -> Vec<usize> {
+        let mut scored: Vec<(i64, usize)> = (0..self.items.len())
+            .filter(|&idx| !self.items[idx].is_dir)
+            .filter_map(|idx| {
+                fuzzy_score(&self.relative_path_of(idx), &self.fuzzy_input).map(|s| (s, idx))
+            })
+            .collect();
+        // Higher score first; ties keep tree order.
+        scored.sort_by(|(score_a, idx_a), (score_b, idx_b)| {
+            score_b.cmp(score_a).then(idx_a.cmp(idx_b))
+        });
+        scored.into_iter().map(|(_, idx)| idx).collect()
+    }
+
+    fn apply_fuzzy_selection(&mut self) {
+        let chosen: Vec<usize> = if self.fuzzy_marked.is_empty() {
+            // Nothing marked with Tab: take the highlighted result.
+            self.fuzzy_ranked_file_indices()
+                .get(self.fuzzy_cursor)
+                .copied()
+                .into_iter()
+                .collect()
+        } else {
+            self.fuzzy_marked.iter().copied().collect()
+        };
+        self.select_files(chosen);
+    }
+
+    fn select_files(&mut self, chosen: Vec<usize>) {
+        for item_idx in chosen {
+            apply_state_and_propagate_down_vec(
+                &mut self.items,
+                item_idx,
+                SelectionState::FullySelected,
+            );
+            update_all_parent_states_from_child_vec(&mut self.items, item_idx);
         }
     }
 
+    fn close_fuzzy_overlay(&mut self) {
+        self.mode = AppMode::Normal;
+        self.fuzzy_input.clear();
+        self.fuzzy_cursor = 0;
+        self.fuzzy_marked.clear();
+    }
+
+    /// Case-insensitive search of file contents for `grep_input`. Results are files with at
+    /// least one matching line, most matches first. Binary files and files above
+    /// GREP_MAX_FILE_BYTES are skipped.
+    fn run_grep_search(&mut self) {
+        let query = self.grep_input.to_lowercase();
+        let mut results: Vec<(usize, usize)> = Vec::new();
+        if !query.is_empty() {
+            for (idx, item) in self.items.iter().enumerate() {
+                if item.is_dir || item.size > GREP_MAX_FILE_BYTES {
+                    continue;
+                }
+                let Ok(bytes) = std::fs::read(&item.path) else {
+                    continue;
+                };
+                if crate::utils::looks_binary(&bytes) {
+                    continue;
+                }
+                let matching_lines = String::from_utf8_lossy(&bytes)
+                    .lines()
+                    .filter(|line| line.to_lowercase().contains(&query))
+                    .count();
+                if matching_lines > 0 {
+                    results.push((idx, matching_lines));
+                }
+            }
+        }
+        // Most matches first; ties keep tree order.
+        results.sort_by(|(idx_a, count_a), (idx_b, count_b)| {
+            count_b.cmp(count_a).then(idx_a.cmp(idx_b))
+        });
+        self.grep_results = Some(results);
+        self.grep_cursor = 0;
+        self.grep_marked.clear();
+    }
+
+    fn apply_grep_selection(&mut self) {
+        let chosen: Vec<usize> = if self.grep_marked.is_empty() {
+            self.grep_results
+                .as_ref()
+                .and_then(|results| results.get(self.grep_cursor))
+                .map(|&(item_idx, _)| item_idx)
+                .into_iter()
+                .collect()
+        } else {
+            self.grep_marked.iter().copied().collect()
+        };
+        self.select_files(chosen);
+    }
+
+    fn close_grep_overlay(&mut self) {
+        self.mode = AppMode::Normal;
+        self.grep_input.clear();
+        self.grep_results = None;
+        self.grep_cursor = 0;
+        self.grep_marked.clear();
+    }
+
     pub(super) fn select_next_visible_item(&mut self) {
         self.move_selection_in_visible_list(1);
     }
@@ -141,6 +405,50 @@ impl TuiApp {
         }
     }
 
+    // Like move_selection_in_visible_list, but stops at the ends instead of wrapping around.
+    pub(super) fn move_selection_clamped(&mut self, delta: isize) {
+        let visible_indices = self.get_visible_item_indices();
+        if visible_indices.is_empty() {
+            return;
+        }
+        let current_pos = visible_indices
+            .iter()
+            .position(|&idx| idx == self.current_selection_idx)
+            .unwrap_or(0);
+        let new_pos = current_pos
+            .saturating_add_signed(delta)
+            .min(visible_indices.len() - 1);
+        self.current_selection_idx = visible_indices[new_pos];
+        self.ensure_selection_is_visible_in_viewport();
+    }
+
+    pub(super) fn select_first_visible_item(&mut self) {
+        if let Some(&first_idx) = self.get_visible_item_indices().first() {
+            self.current_selection_idx = first_idx;
+            self.ensure_selection_is_visible_in_viewport();
+        }
+    }
+
+    pub(super) fn select_last_visible_item(&mut self) {
+        if let Some(&last_idx) = self.get_visible_item_indices().last() {
+            self.current_selection_idx = last_idx;
+            self.ensure_selection_is_visible_in_viewport();
+        }
+    }
+
+    /// Highlights the `position`th visible item, counting from 1 like vim's `42G`. Positions
+    /// past the end land on the last item.
+    pub(super) fn select_nth_visible_item(&mut self, position: usize) {
+        let visible_indices = self.get_visible_item_indices();
+        if let Some(&idx) = visible_indices
+            .get(position.saturating_sub(1))
+            .or(visible_indices.last())
+        {
+            self.current_selection_idx = idx;
+            self.ensure_selection_is_visible_in_viewport();
+        }
+    }
+
     pub(super) fn toggle_current_item_selection(&mut self) {
         if self.items.is_empty() || self.current_selection_idx >= self.items.len() {
             return;
@@ -157,6 +465,26 @@ impl TuiApp {
         update_all_parent_states_from_child_vec(&mut self.items, item_idx);
     }
 
+    /// Replaces the whole selection with just the highlighted item (for a directory: every
+    /// file under it) and confirms immediately.
+    pub(super) fn yank_highlighted_only(&mut self) {
+        if self.current_selection_idx >= self.items.len() {
+            return;
+        }
+        for item in self.items.iter_mut() {
+            item.state = SelectionState::NotSelected;
+        }
+        let item_idx = self.current_selection_idx;
+        apply_state_and_propagate_down_vec(
+            &mut self.items,
+            item_idx,
+            SelectionState::FullySelected,
+        );
+        update_all_parent_states_from_child_vec(&mut self.items, item_idx);
+        self.confirmed = true;
+        self.quit = true;
+    }
+
     pub(super) fn select_all_visible_items(&mut self) {
         let visible_indices = self.get_visible_item_indices();
         for &item_idx in &visible_indices {
@@ -171,6 +499,87 @@ impl TuiApp {
         }
     }
 
+    /// Selects exactly the files the filter left visible (files inside collapsed directories
+    /// are not shown, so they stay as they were), then clears the text filter to show the
+    /// result in the full tree.
+    fn select_filtered_and_clear_filter(&mut self) {
+        if self.filter_input.is_empty() && !matches!(self.age_view, AgeView::Only(_)) {
+            self.status_message = Some("No filter is active; use / to filter first".to_string());
+            return;
+        }
+        let filtered_files = self
+            .get_visible_item_indices()
+            .into_iter()
+            .filter(|&item_idx| !self.items[item_idx].is_dir)
+            .count();
+        // Recomputes the parent states after every file, so directories above the filtered
+        // files end up partially or fully selected as appropriate.
+        self.select_all_visible_items();
+        self.filter_input.clear();
+        self.refresh_filter();
+        self.filter_cursor_pos = 0;
+        self.ensure_selection_is_valid_after_filter();
+        self.status_message = Some(format!("Selected {} filtered files", filtered_files));
+    }
+
+    /// Selects every visible file with the extension typed at the `t` prompt, or deselects
+    /// them all if they already are. Matching follows `--type`: case-insensitive, and
+    /// compound extensions like `tar.gz` work.
+    fn toggle_visible_files_with_extension(&mut self) {
+        let input = self.extension_input.trim();
+        let ext = input
+            .strip_prefix("*.")
+            .or_else(|| input.strip_prefix('.'))
+            .unwrap_or(input)
+            .to_lowercase();
+        if ext.is_empty() {
+            self.status_message = Some("No extension given".to_string());
+            return;
+        }
+        if ext.contains(['/', '\\', '*', ' ']) {
+            self.status_message = Some(format!("'{}' is not a file extension", input));
+            return;
+        }
+        let suffix = format!(".{}", ext);
+        let matching: Vec<usize> = self
+            .get_visible_item_indices()
+            .into_iter()
+            .filter(|&item_idx| {
+                let item = &self.items[item_idx];
+                !item.is_dir
+                    && item.path.file_name().is_some_and(|name| {
+                        name.to_string_lossy().to_lowercase().ends_with(&suffix)
+                    })
+            })
+            .collect();
+        if matching.is_empty() {
+            self.status_message = Some(format!("No visible {} files", suffix));
+            return;
+        }
+        let new_state = if matching
+            .iter()
+            .all(|&item_idx| self.items[item_idx].state == SelectionState::FullySelected)
+        {
+            SelectionState::NotSelected
+        } else {
+            SelectionState::FullySelected
+        };
+        for &item_idx in &matching {
+            apply_state_and_propagate_down_vec(&mut self.items, item_idx, new_state);
+            update_all_parent_states_from_child_vec(&mut self.items, item_idx);
+        }
+        self.status_message = Some(format!(
+            "{} {} {} files",
+            if new_state == SelectionState::FullySelected {
+                "Selected"
+            } else {
+                "Deselected"
+            },
+            matching.len(),
+            suffix
+        ));
+    }
+
     pub(super) fn deselect_all_visible_items(&mut self) {
         let visible_indices = self.get_visible_item_indices();
         for &item_idx in &visible_indices {
@@ -196,73 +605,99 @@ impl TuiApp {
         let root_path_of_tui = self.items.first().map(|item| item.path.clone());
         for item in self.items.iter_mut() {
             if item.is_dir {
-                if item.parent_index.is_some() || Some(&item.path) != root_path_of_tui.as_ref() {
-                    item.is_expanded = false;
-                } else {
-                    item.is_expanded = true;
-                }
+                // Only the TUI root stays expanded.
+                item.is_expanded =
+                    item.parent_index.is_none() && Some(&item.path) == root_path_of_tui.as_ref();
             }
         }
         self.ensure_selection_is_visible(); // This one, not viewport specific
     }
 
     pub(super) fn get_visible_item_indices(&self) -> Vec<usize> {
-        let mut visible_indices = Vec::new();
-        let filter_active = !self.filter_input.is_empty();
-        let lower_filter = self.filter_input.to_lowercase();
+        let filter_active =
+            !self.filter_input.is_empty() || matches!(self.age_view, AgeView::Only(_));
+        // An invalid regex leaves the list unfiltered; the error is shown in the filter title.
+        let unfiltered = FilterMatcher::Substring(String::new());
+        let matcher = self.filter_matcher().unwrap_or(&unfiltered);
+        let filter_matches = filter_active.then(|| self.items_matching_filter(matcher));
 
+        // Items are in tree order, so a parent's visibility is known before its children's.
+        let mut is_visible = vec![false; self.items.len()];
         for i in 0..self.items.len() {
-            if self.is_item_visible_recursive(i) {
-                if filter_active {
-                    if self.item_matches_filter_or_has_matching_descendant(i, &lower_filter) {
-                        visible_indices.push(i);
-                    }
-                } else {
-                    visible_indices.push(i);
+            is_visible[i] = match self.items[i].parent_index {
+                None => true,
+                Some(parent_idx) if parent_idx < i => {
+                    is_visible[parent_idx] && self.items[parent_idx].is_expanded
                 }
-            }
+                Some(_) => self.is_item_visible(i),
+            };
         }
-        visible_indices
+        (0..self.items.len())
+            .filter(|&i| is_visible[i])
+            .filter(|&i| filter_matches.as_ref().is_none_or(|matches| matches[i]))
+            .collect()
+    }
+
+    /// `filter_input` compiled for the current filter mode, or why the regex is invalid.
+    pub(super) fn filter_matcher(&self) -> Result<&FilterMatcher, &regex::Error> {
+        self.compiled_filter.as_ref()
     }
 
-    pub(super) fn is_item_visible_recursive(&self, item_idx: usize) -> bool {
+    /// Recompiles the filter; called whenever `filter_input` or `filter_is_regex` changes,
+    /// so the visible-item list does not rebuild a regex on every redraw.
+    pub(super) fn refresh_filter(&mut self) {
+        self.compiled_filter = compile_filter(&self.filter_input, self.filter_is_regex);
+    }
+
+    /// Whether every ancestor of the item is expanded. Walks the parent chain iteratively
+    /// so arbitrarily deep trees can't overflow the stack.
+    pub(super) fn is_item_visible(&self, item_idx: usize) -> bool {
         if item_idx >= self.items.len() {
             return false;
         }
-        let item = &self.items[item_idx];
-        match item.parent_index {
-            None => true,
-            Some(parent_idx) => {
-                if parent_idx >= self.items.len() {
-                    return false;
-                }
-                self.items[parent_idx].is_expanded && self.is_item_visible_recursive(parent_idx)
+        let mut current_idx = item_idx;
+        while let Some(parent_idx) = self.items[current_idx].parent_index {
+            if parent_idx >= self.items.len() || !self.items[parent_idx].is_expanded {
+                return false;
             }
+            current_idx = parent_idx;
         }
+        true
     }
 
-    pub(super) fn item_matches_filter_or_has_matching_descendant(
-        &self,
-        item_idx: usize,
-        lower_filter: &str,
-    ) -> bool {
-        if item_idx >= self.items.len() {
-            return false;
-        }
-        let item = &self.items[item_idx];
-        if item.display_text.to_lowercase().contains(lower_filter) {
-            return true;
-        }
-        if item.is_dir {
-            for &child_idx in &item.children_indices {
-                if self.item_matches_filter_or_has_matching_descendant(child_idx, lower_filter) {
-                    return true;
+    /// For every item, whether it or one of its descendants matches the filter, so that
+    /// parents of matches stay visible. Computed in one pass over the items.
+    pub(super) fn items_matching_filter(&self, matcher: &FilterMatcher) -> Vec<bool> {
+        let mut matches = vec![false; self.items.len()];
+        for (item_idx, item) in self.items.iter().enumerate() {
+            // An age bucket only applies to files; directories show through matching descendants.
+            let age_matches = match self.age_view {
+                AgeView::Only(bucket) => {
+                    !item.is_dir && AgeBucket::of(item.modified) == Some(bucket)
+                }
+                AgeView::Off | AgeView::All => true,
+            };
+            // Match the root-relative path rather than `display_text`, which carries tree glyphs.
+            if !age_matches || !matcher.matches(&self.relative_path_of(item_idx)) {
+                continue;
+            }
+            // Mark the item and its ancestors, stopping at the first one already marked:
+            // everything above it was marked by an earlier match.
+            let mut current_idx = Some(item_idx);
+            while let Some(idx) = current_idx {
+                if idx >= matches.len() || matches[idx] {
+                    break;
                 }
+                matches[idx] = true;
+                current_idx = self.items[idx].parent_index;
             }
         }
-        false
+        matches
     }
 
+    // Filtering is purely a visibility predicate layered on top of the tree: this only moves
+    // the cursor and scroll offset and must never write `is_expanded`, so applying and then
+    // clearing a filter leaves the expansion state exactly as the user left it.
     pub(super) fn ensure_selection_is_valid_after_filter(&mut self) {
         let visible_indices = self.get_visible_item_indices();
         if visible_indices.is_empty() {
@@ -309,7 +744,6 @@ impl TuiApp {
         } else {
             self.scroll_offset = self.scroll_offset.min(num_visible_items - list_height);
         }
-        self.scroll_offset = self.scroll_offset.max(0);
     }
 
     pub(super) fn toggle_expansion_and_adjust_selection(&mut self) {
@@ -323,12 +757,31 @@ impl TuiApp {
         }
     }
 
+    /// Sets `is_expanded` on the highlighted directory and on its immediate child
+    /// directories, leaving anything deeper as it is (`O` expands, `C` collapses).
+    pub(super) fn set_expansion_one_level(&mut self, expanded: bool) {
+        let Some(item) = self.items.get(self.current_selection_idx) else {
+            return;
+        };
+        if !item.is_dir {
+            return;
+        }
+        let mut level = item.children_indices.clone();
+        level.push(self.current_selection_idx);
+        for idx in level {
+            if self.items[idx].is_dir {
+                self.items[idx].is_expanded = expanded;
+            }
+        }
+        self.ensure_selection_is_visible();
+    }
+
     // This is the original ensure_selection_is_visible, focused on hierarchical adjustment
     pub(super) fn ensure_selection_is_visible(&mut self) {
         if self.items.is_empty() {
             return;
         }
-        if self.is_item_visible_recursive(self.current_selection_idx) {
+        if self.is_item_visible(self.current_selection_idx) {
             // Use hierarchical check
             self.ensure_selection_is_visible_in_viewport(); // Then adjust viewport
             return;
@@ -340,14 +793,14 @@ impl TuiApp {
             .and_then(|item| item.parent_index)
         {
             candidate_idx = parent_idx;
-            if self.is_item_visible_recursive(candidate_idx) {
+            if self.is_item_visible(candidate_idx) {
                 self.current_selection_idx = candidate_idx;
                 self.ensure_selection_is_visible_in_viewport();
                 return;
             }
         }
         // If loop finishes, candidate_idx is likely a root item. Check its visibility.
-        if self.is_item_visible_recursive(candidate_idx) {
+        if self.is_item_visible(candidate_idx) {
             self.current_selection_idx = candidate_idx;
             self.ensure_selection_is_visible_in_viewport();
             return;
@@ -365,38 +818,250 @@ impl TuiApp {
 
     // --- Event handling sub-methods ---
     pub(super) fn handle_normal_mode_input(&mut self, key_event: KeyEvent) {
+        if self.show_help {
+            self.handle_help_overlay_input(key_event);
+            return;
+        }
+        // Any key other than a second `g` cancels a pending `gg`, and any key other than a
+        // digit or `G` drops a pending count.
+        let pending_g = std::mem::take(&mut self.pending_g);
+        let pending_count = self.pending_count.take();
+        self.status_message = None;
         match key_event.code {
+            // A count starts at 1-9, so a lone `0` stays free.
+            KeyCode::Char(digit @ '0'..='9')
+                if key_event.modifiers.is_empty() && (pending_count.is_some() || digit != '0') =>
+            {
+                let value = digit.to_digit(10).unwrap_or(0) as usize;
+                self.pending_count = Some(
+                    pending_count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(value),
+                );
+            }
+            KeyCode::Char('g') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.mode = AppMode::Grep;
+            }
+            KeyCode::Char('g') if pending_g => self.select_first_visible_item(),
+            KeyCode::Char('g') => self.pending_g = true,
+            KeyCode::Char('G') => match pending_count {
+                Some(position) => self.select_nth_visible_item(position),
+                None => self.select_last_visible_item(),
+            },
+            KeyCode::PageDown => {
+                self.move_selection_clamped(self.list_viewport_height.max(1) as isize)
+            }
+            KeyCode::PageUp => {
+                self.move_selection_clamped(-(self.list_viewport_height.max(1) as isize))
+            }
+            KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.move_selection_clamped((self.list_viewport_height / 2).max(1) as isize);
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.move_selection_clamped(-((self.list_viewport_height / 2).max(1) as isize));
+            }
             KeyCode::Char('/') => {
                 self.mode = AppMode::Filtering;
             }
+            KeyCode::Char('f') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.mode = AppMode::Fuzzy;
+            }
             KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
             KeyCode::Char('y') => {
                 self.confirmed = true;
                 self.quit = true;
             }
+            KeyCode::Char('Y') => self.yank_highlighted_only(),
+            KeyCode::Char('E') => self.request_edit_of_highlighted(),
+            KeyCode::Char('c') if key_event.modifiers.is_empty() => self.copy_request = true,
+            KeyCode::Char('F') => self.select_filtered_and_clear_filter(),
+            KeyCode::Char('t') => {
+                self.extension_input.clear();
+                self.mode = AppMode::ExtensionPrompt;
+            }
             KeyCode::Down | KeyCode::Char('j') => self.select_next_visible_item(),
             KeyCode::Up | KeyCode::Char('k') => self.select_previous_visible_item(),
             KeyCode::Char(' ') | KeyCode::Enter => self.toggle_current_item_selection(),
             KeyCode::Char('o') | KeyCode::Tab => self.toggle_expansion_and_adjust_selection(),
             KeyCode::Char('*') => self.expand_all_directories(),
             KeyCode::Char('-') => self.collapse_all_directories(),
-            KeyCode::Char('a') => {
-                if key_event.modifiers.is_empty() || key_event.modifiers == KeyModifiers::CONTROL {
-                    self.select_all_visible_items();
-                }
+            KeyCode::Char('O') => self.set_expansion_one_level(true),
+            KeyCode::Char('C') => self.set_expansion_one_level(false),
+            KeyCode::Char('p') => self.show_preview = !self.show_preview,
+            KeyCode::Char('s') => self.show_sizes = !self.show_sizes,
+            KeyCode::Char('?') => {
+                self.show_help = true;
+                self.help_scroll = 0;
+            }
+            KeyCode::Char('m') => {
+                self.age_view = self.age_view.next();
+                self.ensure_selection_is_valid_after_filter();
+            }
+            KeyCode::Char('a')
+                if (key_event.modifiers.is_empty()
+                    || key_event.modifiers == KeyModifiers::CONTROL) =>
+            {
+                self.select_all_visible_items();
             }
             KeyCode::Char('A') if key_event.modifiers == KeyModifiers::CONTROL => {
                 self.select_all_visible_items();
             }
-            KeyCode::Char('d') => {
-                if key_event.modifiers.is_empty() {
-                    self.deselect_all_visible_items();
+            KeyCode::Char('d') if key_event.modifiers.is_empty() => {
+                self.deselect_all_visible_items();
+            }
+            _ => {}
+        }
+    }
+
+    // The help overlay swallows every key; only scrolling and closing do anything.
+    fn handle_help_overlay_input(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('?') | KeyCode::Esc | KeyCode::Char('q') => self.show_help = false,
+            KeyCode::Down | KeyCode::Char('j') => self.help_scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.help_scroll = self.help_scroll.saturating_sub(1)
+            }
+            KeyCode::PageDown => self.help_scroll += self.list_viewport_height.max(1),
+            KeyCode::PageUp => {
+                self.help_scroll = self
+                    .help_scroll
+                    .saturating_sub(self.list_viewport_height.max(1))
+            }
+            _ => {}
+        }
+    }
+
+    /// Clicking a row highlights it; clicking its `[+]` or `[ ]` prefix also toggles
+    /// expansion or selection. The scroll wheel moves through the list.
+    pub(super) fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if self.show_help {
+            return;
+        }
+        match mouse_event.kind {
+            MouseEventKind::ScrollDown => self.move_selection_clamped(MOUSE_SCROLL_LINES),
+            MouseEventKind::ScrollUp => self.move_selection_clamped(-MOUSE_SCROLL_LINES),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let area = self.list_area;
+                // Rows live inside the block border.
+                if mouse_event.column <= area.x
+                    || mouse_event.row <= area.y
+                    || mouse_event.column >= area.x + area.width.saturating_sub(1)
+                    || mouse_event.row >= area.y + area.height.saturating_sub(1)
+                {
+                    return;
+                }
+                let row_in_view = (mouse_event.row - area.y - 1) as usize;
+                let visible_indices = self.get_visible_item_indices();
+                let Some(&item_idx) = visible_indices.get(self.scroll_offset + row_in_view) else {
+                    return;
+                };
+                self.current_selection_idx = item_idx;
+                let column_in_row =
+                    (mouse_event.column - area.x - 1).checked_sub(ROW_HIGHLIGHT_WIDTH);
+                match column_in_row {
+                    Some(col) if col < ROW_PREFIX_WIDTH => {
+                        self.toggle_expansion_and_adjust_selection()
+                    }
+                    Some(col) if col < 2 * ROW_PREFIX_WIDTH => self.toggle_current_item_selection(),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn handle_fuzzy_mode_input(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => self.close_fuzzy_overlay(),
+            KeyCode::Enter => {
+                self.apply_fuzzy_selection();
+                self.close_fuzzy_overlay();
+            }
+            KeyCode::Tab => {
+                if let Some(&item_idx) = self.fuzzy_ranked_file_indices().get(self.fuzzy_cursor)
+                    && !self.fuzzy_marked.remove(&item_idx)
+                {
+                    self.fuzzy_marked.insert(item_idx);
+                }
+                self.fuzzy_cursor += 1;
+                self.clamp_fuzzy_cursor();
+            }
+            KeyCode::Down => {
+                self.fuzzy_cursor += 1;
+                self.clamp_fuzzy_cursor();
+            }
+            KeyCode::Up => self.fuzzy_cursor = self.fuzzy_cursor.saturating_sub(1),
+            KeyCode::Backspace => {
+                self.fuzzy_input.pop();
+                self.fuzzy_cursor = 0;
+            }
+            KeyCode::Char(c) => {
+                self.fuzzy_input.push(c);
+                self.fuzzy_cursor = 0;
+            }
+            _ => {}
+        }
+    }
+
+    // Enter first runs the search for the typed query; once results are shown, Enter selects
+    // the marked files (or the highlighted one). Editing the query discards stale results.
+    pub(super) fn handle_grep_mode_input(&mut self, key_event: KeyEvent) {
+        let num_results = self.grep_results.as_ref().map_or(0, Vec::len);
+        match key_event.code {
+            KeyCode::Esc => self.close_grep_overlay(),
+            KeyCode::Enter if self.grep_results.is_none() => self.run_grep_search(),
+            KeyCode::Enter => {
+                self.apply_grep_selection();
+                self.close_grep_overlay();
+            }
+            KeyCode::Tab => {
+                if let Some(&(item_idx, _)) = self
+                    .grep_results
+                    .as_ref()
+                    .and_then(|results| results.get(self.grep_cursor))
+                    && !self.grep_marked.remove(&item_idx)
+                {
+                    self.grep_marked.insert(item_idx);
                 }
+                self.grep_cursor = (self.grep_cursor + 1).min(num_results.saturating_sub(1));
+            }
+            KeyCode::Down => {
+                self.grep_cursor = (self.grep_cursor + 1).min(num_results.saturating_sub(1));
+            }
+            KeyCode::Up => self.grep_cursor = self.grep_cursor.saturating_sub(1),
+            KeyCode::Backspace => {
+                self.grep_input.pop();
+                self.grep_results = None;
+            }
+            KeyCode::Char(c) => {
+                self.grep_input.push(c);
+                self.grep_results = None;
             }
             _ => {}
         }
     }
 
+    pub(super) fn handle_extension_prompt_input(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => self.mode = AppMode::Normal,
+            KeyCode::Enter => {
+                self.mode = AppMode::Normal;
+                self.toggle_visible_files_with_extension();
+            }
+            KeyCode::Backspace => {
+                self.extension_input.pop();
+            }
+            KeyCode::Char(c) => self.extension_input.push(c),
+            _ => {}
+        }
+    }
+
+    fn clamp_fuzzy_cursor(&mut self) {
+        let num_results = self.fuzzy_ranked_file_indices().len();
+        self.fuzzy_cursor = self.fuzzy_cursor.min(num_results.saturating_sub(1));
+    }
+
     pub(super) fn handle_filtering_mode_input(&mut self, key_event: KeyEvent) {
         match key_event.code {
             KeyCode::Enter => {
@@ -406,58 +1071,167 @@ impl TuiApp {
             KeyCode::Esc => {
                 self.mode = AppMode::Normal;
                 self.filter_input.clear();
+                self.refresh_filter();
                 self.filter_cursor_pos = 0;
                 self.ensure_selection_is_valid_after_filter();
             }
+            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.filter_is_regex = !self.filter_is_regex;
+                self.refresh_filter();
+                self.ensure_selection_is_valid_after_filter();
+            }
             KeyCode::Char(c) => {
                 self.filter_input.insert(self.filter_cursor_pos, c);
+                self.refresh_filter();
                 self.filter_cursor_pos += 1;
                 self.ensure_selection_is_valid_after_filter();
             }
-            KeyCode::Backspace => {
-                if self.filter_cursor_pos > 0 && !self.filter_input.is_empty() {
-                    self.filter_cursor_pos -= 1;
-                    self.filter_input.remove(self.filter_cursor_pos);
-                    self.ensure_selection_is_valid_after_filter();
-                }
+            KeyCode::Backspace if self.filter_cursor_pos > 0 && !self.filter_input.is_empty() => {
+                self.filter_cursor_pos -= 1;
+                self.filter_input.remove(self.filter_cursor_pos);
+                self.refresh_filter();
+                self.ensure_selection_is_valid_after_filter();
             }
-            KeyCode::Left => {
-                if self.filter_cursor_pos > 0 {
-                    self.filter_cursor_pos -= 1;
-                }
+            KeyCode::Left if self.filter_cursor_pos > 0 => {
+                self.filter_cursor_pos -= 1;
             }
-            KeyCode::Right => {
-                if self.filter_cursor_pos < self.filter_input.len() {
-                    self.filter_cursor_pos += 1;
-                }
+            KeyCode::Right if self.filter_cursor_pos < self.filter_input.len() => {
+                self.filter_cursor_pos += 1;
             }
             _ => {}
         }
     }
 }
 
+// Compiles a TUI filter. Both modes use smart case: the match ignores case unless the filter
+// contains an uppercase letter.
+fn compile_filter(filter: &str, is_regex: bool) -> Result<FilterMatcher, regex::Error> {
+    let case_sensitive = is_smart_case_sensitive(filter, is_regex);
+    if is_regex {
+        regex::RegexBuilder::new(filter)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(FilterMatcher::Regex)
+    } else if case_sensitive {
+        Ok(FilterMatcher::CaseSensitiveSubstring(filter.to_string()))
+    } else {
+        Ok(FilterMatcher::Substring(filter.to_lowercase()))
+    }
+}
+
+// Whether a filter should match case-sensitively: it contains an uppercase letter. In a regex
+// the letter of an escape such as `\S` or `\W` does not count.
+fn is_smart_case_sensitive(filter: &str, is_regex: bool) -> bool {
+    let mut chars = filter.chars();
+    while let Some(c) = chars.next() {
+        if is_regex && c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+// Case-insensitive subsequence match. Consecutive matches and matches at the start of a path
+// segment or word score higher; gaps and long candidates score lower. None if no match.
+fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0i64;
+    let mut next_pos = 0;
+    let mut prev_match: Option<usize> = None;
+    for query_char in query.to_lowercase().chars() {
+        let offset = candidate[next_pos..]
+            .iter()
+            .position(|&c| c == query_char)?;
+        let pos = next_pos + offset;
+        score += 1;
+        if prev_match.is_some_and(|prev| prev + 1 == pos) {
+            score += 5;
+        }
+        if pos == 0 || matches!(candidate[pos - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 3;
+        }
+        score -= offset as i64;
+        prev_match = Some(pos);
+        next_pos = pos + 1;
+    }
+    Some(score * 100 - candidate.len() as i64)
+}
+
+// Size of every item, with directories holding the total of all their descendants. Children
+// are summed into parents in reverse pre-order, so each item is visited once: O(n).
+fn compute_subtree_sizes(items: &[SelectableItem]) -> Vec<u64> {
+    let mut sizes: Vec<u64> = items
+        .iter()
+        .map(|item| if item.is_dir { 0 } else { item.size })
+        .collect();
+    let mut pre_order = Vec::with_capacity(items.len());
+    let mut stack: Vec<usize> = (0..items.len())
+        .filter(|&idx| items[idx].parent_index.is_none())
+        .collect();
+    while let Some(idx) = stack.pop() {
+        pre_order.push(idx);
+        stack.extend(items[idx].children_indices.iter().copied());
+    }
+    for &idx in pre_order.iter().rev() {
+        if let Some(parent_idx) = items[idx].parent_index {
+            sizes[parent_idx] += sizes[idx];
+        }
+    }
+    sizes
+}
+
+// Reads the first PREVIEW_MAX_LINES lines of a file, or a placeholder for anything that
+// isn't readable UTF-8 text.
+fn load_preview_lines(item: &SelectableItem, syntax_theme: &str) -> Vec<Line<'static>> {
+    if item.is_dir {
+        return vec![Line::from("(directory)")];
+    }
+    let mut bytes = Vec::new();
+    let read_ok = std::fs::File::open(&item.path)
+        .and_then(|file| file.take(PREVIEW_MAX_BYTES).read_to_end(&mut bytes))
+        .is_ok();
+    if !read_ok || crate::utils::looks_binary(&bytes) {
+        return vec![Line::from("[cannot preview]")];
+    }
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text,
+        // The byte cap may split a multi-byte character at the end; keep the valid prefix.
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return vec![Line::from("[cannot preview]")],
+    };
+    let lines = text
+        .lines()
+        .take(PREVIEW_MAX_LINES)
+        .map(str::to_string)
+        .collect();
+    highlight::highlight_preview(&item.path, lines, syntax_theme)
+}
+
 // --- prepare_selectable_items (public to the crate via tui/mod.rs re-export) ---
 pub fn prepare_selectable_items(
-    initial_items_paths_is_dir: &[(PathBuf, bool)],
+    entries: &[ScannedEntry],
     display_labels: &[String],
     root_path: &Path,
 ) -> Vec<SelectableItem> {
     let mut selectable_items = Vec::new();
     let mut path_to_idx_map: HashMap<PathBuf, usize> = HashMap::new();
-    for (i, ((path, is_dir), label)) in initial_items_paths_is_dir
-        .iter()
-        .zip(display_labels.iter())
-        .enumerate()
-    {
-        path_to_idx_map.insert(path.clone(), i);
+    for (i, (entry, label)) in entries.iter().zip(display_labels.iter()).enumerate() {
+        path_to_idx_map.insert(entry.path.clone(), i);
+        // Size and modification time come from the walk, so nothing is statted again here.
         selectable_items.push(SelectableItem {
-            path: path.clone(),
+            path: entry.path.clone(),
             display_text: label.clone(),
-            is_dir: *is_dir,
-            is_expanded: *is_dir, // Default to expanded
+            is_dir: entry.is_dir,
+            is_expanded: entry.is_dir, // Default to expanded
             state: SelectionState::NotSelected,
             children_indices: Vec::new(),
             parent_index: None,
+            modified: entry.modified,
+            size: entry.size,
         });
     }
     for i in 0..selectable_items.len() {
@@ -467,17 +1241,242 @@ pub fn prepare_selectable_items(
             && path.parent().is_some()
             && (path.parent().unwrap() == root_path
                 || path.parent().unwrap().starts_with(root_path))
+            && let Some(parent_pbuf) = path.parent()
+            && let Some(parent_idx) = path_to_idx_map.get(parent_pbuf)
         {
-            if let Some(parent_pbuf) = path.parent() {
-                if let Some(parent_idx) = path_to_idx_map.get(parent_pbuf) {
-                    selectable_items[i].parent_index = Some(*parent_idx);
-                    if selectable_items[*parent_idx].is_dir {
-                        // Ensure parent is actually a directory
-                        selectable_items[*parent_idx].children_indices.push(i);
-                    }
-                }
+            selectable_items[i].parent_index = Some(*parent_idx);
+            if selectable_items[*parent_idx].is_dir {
+                // Ensure parent is actually a directory
+                selectable_items[*parent_idx].children_indices.push(i);
             }
         }
     }
     selectable_items
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(path: &str, is_dir: bool, parent_index: Option<usize>) -> SelectableItem {
+        SelectableItem {
+            path: PathBuf::from(path),
+            display_text: path.to_string(),
+            is_dir,
+            is_expanded: true,
+            state: SelectionState::NotSelected,
+            children_indices: Vec::new(),
+            parent_index,
+            modified: None,
+            size: 0,
+        }
+    }
+
+    // root/ { src/ { a.rs, b.rs }, README.md }, everything expanded.
+    fn sample_items() -> Vec<SelectableItem> {
+        let mut items = vec![
+            item("root", true, None),
+            item("root/src", true, Some(0)),
+            item("root/src/a.rs", false, Some(1)),
+            item("root/src/b.rs", false, Some(1)),
+            item("root/README.md", false, Some(0)),
+        ];
+        items[0].children_indices = vec![1, 4];
+        items[1].children_indices = vec![2, 3];
+        items
+    }
+
+    // A chain of `depth` nested directories ending in one file.
+    fn deep_chain(depth: usize) -> Vec<SelectableItem> {
+        (0..=depth)
+            .map(|i| {
+                let mut chain_item = item(&format!("d{}", i), i < depth, i.checked_sub(1));
+                if i < depth {
+                    chain_item.children_indices = vec![i + 1];
+                }
+                chain_item
+            })
+            .collect()
+    }
+
+    fn states(items: &[SelectableItem]) -> Vec<SelectionState> {
+        items.iter().map(|item| item.state).collect()
+    }
+
+    use SelectionState::{FullySelected as Full, NotSelected as Not, PartiallySelected as Partial};
+
+    #[test]
+    fn selecting_a_directory_selects_its_subtree() {
+        let mut items = sample_items();
+        apply_state_and_propagate_down_vec(&mut items, 1, Full);
+        update_all_parent_states_from_child_vec(&mut items, 1);
+        assert_eq!(states(&items), vec![Partial, Full, Full, Full, Not]);
+
+        apply_state_and_propagate_down_vec(&mut items, 1, Not);
+        update_all_parent_states_from_child_vec(&mut items, 1);
+        assert_eq!(states(&items), vec![Not, Not, Not, Not, Not]);
+    }
+
+    #[test]
+    fn one_selected_child_makes_every_ancestor_partial() {
+        let mut items = sample_items();
+        apply_state_and_propagate_down_vec(&mut items, 2, Full);
+        update_all_parent_states_from_child_vec(&mut items, 2);
+        assert_eq!(states(&items), vec![Partial, Partial, Full, Not, Not]);
+    }
+
+    #[test]
+    fn parents_become_full_once_all_children_are() {
+        let mut items = sample_items();
+        for idx in [2, 3] {
+            apply_state_and_propagate_down_vec(&mut items, idx, Full);
+            update_all_parent_states_from_child_vec(&mut items, idx);
+        }
+        assert_eq!(states(&items), vec![Partial, Full, Full, Full, Not]);
+
+        apply_state_and_propagate_down_vec(&mut items, 4, Full);
+        update_all_parent_states_from_child_vec(&mut items, 4);
+        assert_eq!(states(&items), vec![Full, Full, Full, Full, Full]);
+    }
+
+    #[test]
+    fn a_partial_state_stays_on_the_directory_and_becomes_full_on_files() {
+        let mut items = sample_items();
+        apply_state_and_propagate_down_vec(&mut items, 1, Partial);
+        assert_eq!(states(&items)[1..4], [Partial, Not, Not]);
+
+        apply_state_and_propagate_down_vec(&mut items, 2, Partial);
+        assert_eq!(items[2].state, Full);
+    }
+
+    #[test]
+    fn propagation_handles_deep_trees_without_recursion() {
+        let depth = 100_000;
+        let mut items = deep_chain(depth);
+        apply_state_and_propagate_down_vec(&mut items, 0, Full);
+        assert!(items.iter().all(|item| item.state == Full));
+
+        apply_state_and_propagate_down_vec(&mut items, depth, Not);
+        update_all_parent_states_from_child_vec(&mut items, depth);
+        assert!(items.iter().all(|item| item.state == Not));
+    }
+
+    fn app(items: Vec<SelectableItem>) -> TuiApp {
+        TuiApp::new(items, Path::new("root"), Theme::Default)
+    }
+
+    #[test]
+    fn collapsed_directories_hide_their_descendants() {
+        let mut items = sample_items();
+        items[1].is_expanded = false;
+        let app = app(items);
+        assert_eq!(app.get_visible_item_indices(), vec![0, 1, 4]);
+        assert!(!app.is_item_visible(2));
+        assert!(app.is_item_visible(4));
+    }
+
+    #[test]
+    fn a_collapsed_ancestor_hides_items_under_expanded_parents() {
+        let mut items = sample_items();
+        items[0].is_expanded = false;
+        let app = app(items);
+        assert_eq!(app.get_visible_item_indices(), vec![0]);
+        // src itself is still expanded, but root is not.
+        assert!(!app.is_item_visible(2));
+        assert!(!app.is_item_visible(1));
+    }
+
+    #[test]
+    fn the_filter_keeps_ancestors_of_matches_visible() {
+        let mut app = app(sample_items());
+        app.filter_input = "a.rs".to_string();
+        app.refresh_filter();
+        assert_eq!(app.get_visible_item_indices(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn a_filter_cycle_leaves_expansion_state_alone() {
+        let mut items = sample_items();
+        items[1].is_expanded = false;
+        let mut app = app(items);
+        app.list_viewport_height = 10;
+        let expansion =
+            |app: &TuiApp| -> Vec<bool> { app.items.iter().map(|item| item.is_expanded).collect() };
+        let before = expansion(&app);
+
+        app.handle_normal_mode_input(KeyEvent::from(KeyCode::Char('/')));
+        for c in "b.rs".chars() {
+            app.handle_filtering_mode_input(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_filtering_mode_input(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(expansion(&app), before);
+
+        app.handle_normal_mode_input(KeyEvent::from(KeyCode::Char('/')));
+        app.handle_filtering_mode_input(KeyEvent::from(KeyCode::Esc));
+        assert!(app.filter_input.is_empty());
+        assert_eq!(expansion(&app), before);
+        assert_eq!(app.get_visible_item_indices(), vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn a_lowercase_filter_ignores_case() {
+        let matcher = compile_filter("readme", false).unwrap();
+        assert!(matcher.matches("src/README.md"));
+        assert!(matcher.matches("readme.txt"));
+    }
+
+    #[test]
+    fn an_uppercase_filter_is_case_sensitive() {
+        let matcher = compile_filter("README", false).unwrap();
+        assert!(matcher.matches("src/README.md"));
+        assert!(!matcher.matches("readme.txt"));
+    }
+
+    #[test]
+    fn regex_filters_use_smart_case_too() {
+        // `\S` is a class escape, not an uppercase letter, so this stays case-insensitive.
+        let insensitive = compile_filter(r"main\S*\.rs$", true).unwrap();
+        assert!(insensitive.matches("src/Main.rs"));
+        let sensitive = compile_filter(r"^src/M", true).unwrap();
+        assert!(sensitive.matches("src/Main.rs"));
+        assert!(!sensitive.matches("src/main.rs"));
+        assert!(compile_filter("(", true).is_err());
+    }
+
+    #[test]
+    fn visibility_handles_deep_trees_without_recursion() {
+        let depth = 100_000;
+        let mut items = deep_chain(depth);
+        assert_eq!(
+            app(items.clone()).get_visible_item_indices().len(),
+            depth + 1
+        );
+
+        items[depth / 2].is_expanded = false;
+        let app = app(items);
+        assert!(!app.is_item_visible(depth));
+        assert_eq!(app.get_visible_item_indices().len(), depth / 2 + 1);
+    }
+
+    #[test]
+    fn prepared_items_take_size_and_mtime_from_the_scan() {
+        // None of these paths exist, so any value read back was copied from the entries.
+        let modified = Some(std::time::SystemTime::UNIX_EPOCH);
+        let entries = vec![
+            ScannedEntry::directory(PathBuf::from("root")),
+            ScannedEntry {
+                path: PathBuf::from("root/a.rs"),
+                is_dir: false,
+                size: 42,
+                modified,
+                is_symlink: false,
+            },
+        ];
+        let labels = vec!["./".to_string(), "└─ a.rs".to_string()];
+        let items = prepare_selectable_items(&entries, &labels, Path::new("root"));
+        assert_eq!((items[1].size, items[1].modified), (42, modified));
+        assert_eq!((items[0].size, items[0].modified), (0, None));
+        assert_eq!(items[0].children_indices, vec![1]);
+        assert_eq!(items[1].parent_index, Some(0));
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 pub enum SelectionState {
     NotSelected,
     PartiallySelected,
@@ -16,6 +18,8 @@ pub struct SelectableItem {
     pub state: SelectionState,
     pub children_indices: Vec<usize>,
     pub parent_index: Option<usize>,
+    pub modified: Option<SystemTime>,
+    pub size: u64, // Bytes on disk; 0 for directories
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
@@ -23,4 +27,81 @@ pub(super) enum AppMode {
     // pub(super) for use within tui module
     Normal,
     Filtering,
+    Fuzzy,
+    Grep,
+    ExtensionPrompt, // Typing an extension to toggle-select (`t`)
+}
+
+/// Coarse modification-age bucket for a file, relative to now.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(super) enum AgeBucket {
+    Today,
+    ThisWeek,
+    Older,
+}
+
+impl AgeBucket {
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    pub(super) fn of(modified: Option<SystemTime>) -> Option<AgeBucket> {
+        let age = SystemTime::now()
+            .duration_since(modified?)
+            .unwrap_or_default();
+        Some(if age < Self::DAY {
+            AgeBucket::Today
+        } else if age < Self::DAY * 7 {
+            AgeBucket::ThisWeek
+        } else {
+            AgeBucket::Older
+        })
+    }
+}
+
+/// Age view cycled with `m`: off, colour-coded overview, or showing only one bucket.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(super) enum AgeView {
+    Off,
+    All,
+    Only(AgeBucket),
+}
+
+impl AgeView {
+    pub(super) fn next(self) -> AgeView {
+        match self {
+            AgeView::Off => AgeView::All,
+            AgeView::All => AgeView::Only(AgeBucket::Today),
+            AgeView::Only(AgeBucket::Today) => AgeView::Only(AgeBucket::ThisWeek),
+            AgeView::Only(AgeBucket::ThisWeek) => AgeView::Only(AgeBucket::Older),
+            AgeView::Only(AgeBucket::Older) => AgeView::Off,
+        }
+    }
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            AgeView::Off => "off",
+            AgeView::All => "all",
+            AgeView::Only(AgeBucket::Today) => "today",
+            AgeView::Only(AgeBucket::ThisWeek) => "this week",
+            AgeView::Only(AgeBucket::Older) => "older",
+        }
+    }
+}
+
+/// The compiled TUI text filter, matched against each item's path relative to the root.
+pub enum FilterMatcher {
+    /// Case-insensitive substring match; holds the lowercased filter.
+    Substring(String),
+    /// Exact substring match, for a filter with an uppercase letter (smart case).
+    CaseSensitiveSubstring(String),
+    Regex(regex::Regex),
+}
+
+impl FilterMatcher {
+    pub(super) fn matches(&self, text: &str) -> bool {
+        match self {
+            FilterMatcher::Substring(lower_filter) => text.to_lowercase().contains(lower_filter),
+            FilterMatcher::CaseSensitiveSubstring(filter) => text.contains(filter.as_str()),
+            FilterMatcher::Regex(regex) => regex.is_match(text),
+        }
+    }
 }
@@ -6,13 +6,19 @@ use std::time::Duration;
 
 pub(super) fn handle_events(app: &mut TuiApp) -> Result<()> {
     if event::poll(Duration::from_millis(50))? {
-        if let Event::Key(key_event) = event::read()? {
-            if key_event.kind == KeyEventKind::Press {
-                match app.mode {
-                    AppMode::Normal => app.handle_normal_mode_input(key_event),
-                    AppMode::Filtering => app.handle_filtering_mode_input(key_event),
-                }
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => match app.mode {
+                AppMode::Normal => app.handle_normal_mode_input(key_event),
+                AppMode::Filtering => app.handle_filtering_mode_input(key_event),
+                AppMode::Fuzzy => app.handle_fuzzy_mode_input(key_event),
+                AppMode::Grep => app.handle_grep_mode_input(key_event),
+                AppMode::ExtensionPrompt => app.handle_extension_prompt_input(key_event),
+            },
+            // The mouse only drives the main list; overlays and the filter prompt ignore it.
+            Event::Mouse(mouse_event) if app.mode == AppMode::Normal => {
+                app.handle_mouse_event(mouse_event)
             }
+            _ => {}
         }
     }
     Ok(())
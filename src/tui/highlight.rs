@@ -0,0 +1,72 @@
+use ratatui::text::Line;
+use std::path::Path;
+
+/// Turns preview text into renderable lines, syntax-highlighted with the syntect theme
+/// `syntax_theme` when built with the `syntax-highlight` feature. Files with no known syntax
+/// stay plain.
+#[cfg(feature = "syntax-highlight")]
+pub(super) fn highlight_preview(
+    path: &Path,
+    lines: Vec<String>,
+    syntax_theme: &str,
+) -> Vec<Line<'static>> {
+    use ratatui::style::{Color, Style};
+    use ratatui::text::Span;
+    use std::sync::OnceLock;
+    use syntect::easy::HighlightLines;
+    use syntect::highlighting::ThemeSet;
+    use syntect::parsing::SyntaxSet;
+
+    // Loading the bundled definitions takes a noticeable moment, so do it once.
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let themes = THEMES.get_or_init(ThemeSet::load_defaults);
+
+    let extension = crate::utils::extension_key(path);
+    let syntax = syntaxes.find_syntax_by_extension(&extension).or_else(|| {
+        lines
+            .first()
+            .and_then(|first| syntaxes.find_syntax_by_first_line(first))
+    });
+    let (Some(syntax), Some(theme)) = (syntax, themes.themes.get(syntax_theme)) else {
+        return plain_lines(lines);
+    };
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut highlighted = Vec::with_capacity(lines.len());
+    for (idx, line) in lines.iter().enumerate() {
+        // The syntax set expects lines with their newline.
+        let with_newline = format!("{}\n", line);
+        let Ok(regions) = highlighter.highlight_line(&with_newline, syntaxes) else {
+            // The highlighter state is unreliable after an error; finish in plain text.
+            highlighted.extend(plain_lines(lines[idx..].to_vec()));
+            break;
+        };
+        let spans: Vec<Span<'static>> = regions
+            .into_iter()
+            .map(|(style, text)| {
+                let fg = style.foreground;
+                Span::styled(
+                    text.trim_end_matches('\n').to_string(),
+                    Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b)),
+                )
+            })
+            .collect();
+        highlighted.push(Line::from(spans));
+    }
+    highlighted
+}
+
+#[cfg(not(feature = "syntax-highlight"))]
+pub(super) fn highlight_preview(
+    _path: &Path,
+    lines: Vec<String>,
+    _syntax_theme: &str,
+) -> Vec<Line<'static>> {
+    plain_lines(lines)
+}
+
+fn plain_lines(lines: Vec<String>) -> Vec<Line<'static>> {
+    lines.into_iter().map(Line::from).collect()
+}
@@ -2,6 +2,8 @@
 mod app_logic;
 mod app_state;
 mod event_handler;
+mod highlight;
+mod theme;
 mod ui_renderer;
 
 // Re-export necessary items for use by other modules (e.g., workflow.rs)
@@ -22,6 +24,7 @@ mod run_tui {
     use super::app_state::SelectableItem;
     use super::event_handler::handle_events;
     use super::ui_renderer::ui_frame;
+    use crate::cli::Theme;
     use anyhow::Result;
     use crossterm::{
         event::{DisableMouseCapture, EnableMouseCapture},
@@ -31,15 +34,20 @@ mod run_tui {
     use ratatui::prelude::{CrosstermBackend, Terminal};
     use std::io::{self, Stdout};
     use std::path::Path;
+    use std::process::Command;
 
+    /// Runs the TUI until the user confirms or quits. `copy_selection` renders and copies
+    /// the current selection for the `c` key, returning the status line message.
     pub fn run_tui_with_prepared_items(
         prepared_items: Vec<SelectableItem>,
-        #[allow(unused_variables)] root_path: &Path,
+        root_path: &Path,
+        theme: Theme,
+        copy_selection: &mut dyn FnMut(&[SelectableItem]) -> String,
     ) -> Result<Option<Vec<SelectableItem>>> {
         if prepared_items.is_empty() {
             return Ok(None);
         }
-        let mut app = TuiApp::new(prepared_items);
+        let mut app = TuiApp::new(prepared_items, root_path, theme);
 
         let mut terminal = init_terminal()?;
         // Initial call to set up viewport height and ensure selection is visible
@@ -52,6 +60,14 @@ mod run_tui {
             // app.quit is pub(super)
             terminal.draw(|frame| ui_frame(frame, &mut app))?;
             handle_events(&mut app)?;
+            if let Some(item_idx) = app.edit_request.take() {
+                let path = app.items[item_idx].path.clone();
+                app.status_message = edit_file(&mut terminal, &path)?;
+                app.refresh_after_edit(item_idx);
+            }
+            if std::mem::take(&mut app.copy_request) {
+                app.status_message = Some(copy_selection(&app.items));
+            }
         }
 
         restore_terminal(terminal)?;
@@ -71,6 +87,43 @@ mod run_tui {
         Terminal::new(backend).map_err(Into::into)
     }
 
+    // Runs `$EDITOR <path>` with the TUI suspended. The app state lives on untouched, so
+    // the selection is exactly as before. Returns a message for the status line on failure.
+    fn edit_file(
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        path: &Path,
+    ) -> Result<Option<String>> {
+        let editor = std::env::var("EDITOR").unwrap_or_default();
+        // $EDITOR may carry arguments, e.g. `code --wait`.
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            return Ok(Some("$EDITOR is not set".to_string()));
+        };
+
+        disable_raw_mode()?;
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+        terminal.show_cursor()?;
+        let status = Command::new(program).args(words).arg(path).status();
+        enable_raw_mode()?;
+        execute!(
+            terminal.backend_mut(),
+            EnterAlternateScreen,
+            EnableMouseCapture
+        )?;
+        // The editor drew over the screen; force a full redraw.
+        terminal.clear()?;
+
+        Ok(match status {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("{} exited with {}", program, status)),
+            Err(e) => Some(format!("Could not run {}: {}", program, e)),
+        })
+    }
+
     fn restore_terminal(mut terminal: Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
         disable_raw_mode()?;
         execute!(
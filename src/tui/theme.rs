@@ -0,0 +1,82 @@
+use super::app_state::SelectionState;
+use crate::cli::Theme;
+use ratatui::style::{Color, Modifier, Style};
+
+/// Every color the TUI draws with, so a theme is defined in one place.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Palette {
+    pub(super) highlight_bg: Color,
+    pub(super) highlight_fg: Option<Color>, // None keeps the row's own colors under the highlight
+    pub(super) selected: Color,
+    pub(super) partial: Color, // Partially selected directories
+    pub(super) border: Color,
+    pub(super) age_today: Color,
+    pub(super) age_this_week: Color,
+    pub(super) syntax_theme: &'static str, // Bundled syntect theme for the preview
+}
+
+impl Palette {
+    pub(super) fn for_theme(theme: Theme) -> Self {
+        match theme {
+            Theme::Default => Palette {
+                highlight_bg: Color::DarkGray,
+                highlight_fg: None,
+                selected: Color::Green,
+                partial: Color::Yellow,
+                border: Color::Reset,
+                age_today: Color::Green,
+                age_this_week: Color::Yellow,
+                syntax_theme: "base16-ocean.dark",
+            },
+            Theme::Light => Palette {
+                highlight_bg: Color::LightBlue,
+                highlight_fg: Some(Color::Black),
+                selected: Color::Green,
+                partial: Color::Blue,
+                border: Color::DarkGray,
+                age_today: Color::Green,
+                age_this_week: Color::Magenta,
+                syntax_theme: "InspiredGitHub",
+            },
+            Theme::HighContrast => Palette {
+                highlight_bg: Color::White,
+                highlight_fg: Some(Color::Black),
+                selected: Color::LightGreen,
+                partial: Color::LightYellow,
+                border: Color::White,
+                age_today: Color::LightGreen,
+                age_this_week: Color::LightCyan,
+                syntax_theme: "base16-eighties.dark",
+            },
+        }
+    }
+
+    /// Style of the highlighted row in every list.
+    pub(super) fn highlight_style(&self) -> Style {
+        let style = Style::default()
+            .add_modifier(Modifier::BOLD)
+            .bg(self.highlight_bg);
+        match self.highlight_fg {
+            Some(fg) => style.fg(fg),
+            None => style,
+        }
+    }
+
+    /// Style of a row's checkbox and name for its selection state.
+    pub(super) fn selection_style(&self, state: SelectionState) -> Style {
+        match state {
+            SelectionState::NotSelected => Style::default(),
+            SelectionState::PartiallySelected => Style::default().fg(self.partial),
+            SelectionState::FullySelected => Style::default().fg(self.selected),
+        }
+    }
+
+    /// Lower-case name of `color` for legends, e.g. `lightgreen`.
+    pub(super) fn color_name(color: Color) -> String {
+        format!("{:?}", color).to_lowercase()
+    }
+
+    pub(super) fn border_style(&self) -> Style {
+        Style::default().fg(self.border)
+    }
+}
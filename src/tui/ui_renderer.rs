@@ -1,38 +1,123 @@
-use super::app_logic::TuiApp;
-use super::app_state::AppMode;
+use super::app_logic::{ROW_HIGHLIGHT_WIDTH, TuiApp};
+use super::app_state::{AgeBucket, AgeView, AppMode, SelectionState};
+use super::theme::Palette;
+use crate::utils;
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
 };
 
-fn draw_help_block(f: &mut Frame, _app: &TuiApp, area: Rect) {
+// Every normal-mode keybinding, shown by the `?` overlay.
+const KEYBINDINGS: &[(&str, &str)] = &[
+    ("Up/Down, k/j", "Move the highlight"),
+    ("PgUp/PgDn", "Move a full page"),
+    ("Ctrl-U/Ctrl-D", "Move half a page"),
+    ("gg / G", "Jump to the first / last item"),
+    ("NG", "Jump to the Nth visible item, e.g. 42G"),
+    ("Space, Enter", "Toggle selection of the highlighted item"),
+    ("Tab, o", "Expand or collapse the highlighted directory"),
+    (
+        "O / C",
+        "Expand / collapse the highlighted directory and its subdirectories, one level deep",
+    ),
+    ("a, Ctrl-A", "Select all visible items"),
+    ("d", "Deselect all visible items"),
+    (
+        "F",
+        "Select the files matching the filter, then clear the filter",
+    ),
+    (
+        "t",
+        "Select (or deselect) all visible files with an extension (Enter: apply, Esc: cancel)",
+    ),
+    ("*", "Expand all directories"),
+    ("-", "Collapse all directories"),
+    (
+        "/",
+        "Filter the list, smart case (Enter: apply, Esc: cancel, Ctrl-R: regex)",
+    ),
+    (
+        "Ctrl-F",
+        "Fuzzy find files (Tab: mark, Enter: select, Esc: cancel)",
+    ),
+    (
+        "Ctrl-G",
+        "Search file contents (Enter: search, then Tab: mark, Enter: select)",
+    ),
+    ("m", "Cycle the age view"),
+    ("s", "Show or hide sizes (directories: total below them)"),
+    ("p", "Toggle the preview pane"),
+    (
+        "Mouse",
+        "Click a row to highlight, its prefix to fold/select; wheel scrolls",
+    ),
+    ("E", "Open the highlighted file in $EDITOR"),
+    ("c", "Copy the current selection now, without leaving"),
+    ("?", "Toggle this help"),
+    ("y", "Confirm the selection and copy"),
+    (
+        "Y",
+        "Copy only the highlighted file (or directory's files), ignoring the selection",
+    ),
+    ("q, Esc", "Quit without copying"),
+];
+
+fn bordered_block(palette: &Palette) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(palette.border_style())
+}
+
+fn draw_help_block(f: &mut Frame, _app: &TuiApp, palette: &Palette, area: Rect) {
     let help_text_lines_content = vec![
-        Line::from("Arrows/jk: Nav | Space/Enter: Sel | Tab/o: Fold | y: Confirm | q/Esc: Quit"),
-        Line::from("a: Sel All Vis | d: Desel All | *: Expand All | -: Collapse All | /: Filter"),
+        Line::from(
+            "Arrows/jk: Nav | PgUp/PgDn, ^U/^D: Page | gg/G: Top/End | Space/Enter: Sel | Tab/o: Fold | O/C: Fold 1 Lvl | p: Preview | y/Y: Confirm/Only This | q/Esc: Quit",
+        ),
+        Line::from(
+            "a: Sel All Vis | d: Desel All | *: Expand All | -: Collapse All | /: Filter | F: Sel Filtered | t: Sel Ext | ^F: Find | ^G: Grep | m: Age | s: Size | ?: Help",
+        ),
     ];
-    let help_paragraph = Paragraph::new(help_text_lines_content).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Repoyank Interactive Selection"),
-    );
+    let help_paragraph = Paragraph::new(help_text_lines_content)
+        .block(bordered_block(palette).title("Repoyank Interactive Selection"));
     f.render_widget(help_paragraph, area);
 }
 
-fn draw_filter_input_block(f: &mut Frame, app: &TuiApp, area: Rect) {
+fn draw_filter_input_block(f: &mut Frame, app: &TuiApp, palette: &Palette, area: Rect) {
     let input_text = format!("/{}", app.filter_input);
+    let title = match app.filter_matcher() {
+        // The last line of a regex error is the short description.
+        Err(e) => format!(
+            "Regex filter: {} (Ctrl-R: substring)",
+            e.to_string().lines().last().unwrap_or("invalid regex")
+        ),
+        Ok(_) if app.filter_is_regex => {
+            "Regex filter (Esc to cancel, Enter to apply, Ctrl-R: substring)".to_string()
+        }
+        Ok(_) => "Filter (Esc to cancel, Enter to apply, Ctrl-R: regex)".to_string(),
+    };
     let filter_paragraph = Paragraph::new(input_text)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Filter (Esc to cancel, Enter to apply)"),
-        )
+        .block(bordered_block(palette).title(title))
         .wrap(Wrap { trim: false });
     f.render_widget(filter_paragraph, area);
     f.set_cursor_position((area.x + 1 + app.filter_cursor_pos as u16 + 1, area.y + 1));
 }
 
-fn draw_main_list_block(f: &mut Frame, app: &mut TuiApp, area: Rect) {
+fn draw_extension_prompt_block(f: &mut Frame, app: &TuiApp, palette: &Palette, area: Rect) {
+    let input_text = format!("*.{}", app.extension_input);
+    let prompt_paragraph = Paragraph::new(input_text).block(
+        bordered_block(palette)
+            .title("Toggle visible files with extension (Esc to cancel, Enter to apply)"),
+    );
+    f.render_widget(prompt_paragraph, area);
+    f.set_cursor_position((
+        area.x + 1 + 2 + app.extension_input.chars().count() as u16,
+        area.y + 1,
+    ));
+}
+
+fn draw_main_list_block(f: &mut Frame, app: &mut TuiApp, palette: &Palette, area: Rect) {
     app.list_viewport_height = area.height.saturating_sub(2) as usize;
+    app.list_area = area;
     app.ensure_selection_is_visible_in_viewport(); // Call this to adjust scroll based on current state
 
     let visible_item_indices = app.get_visible_item_indices();
@@ -45,41 +130,79 @@ fn draw_main_list_block(f: &mut Frame, app: &mut TuiApp, area: Rect) {
         )
         .unwrap_or(&[]);
 
+    // Sizes are right-aligned against the inner border, after the highlight symbol column.
+    let row_width = area.width.saturating_sub(2 + ROW_HIGHLIGHT_WIDTH) as usize;
     let list_items: Vec<ListItem> = list_items_to_render_indices
         .iter()
         .map(|&item_actual_idx| {
             let item = &app.items[item_actual_idx];
             let selection_prefix = match item.state {
-                super::app_state::SelectionState::NotSelected => "[ ] ",
-                super::app_state::SelectionState::PartiallySelected => "[-] ",
-                super::app_state::SelectionState::FullySelected => "[x] ",
+                SelectionState::NotSelected => "[ ] ",
+                SelectionState::PartiallySelected => "[-] ",
+                SelectionState::FullySelected => "[x] ",
             };
             let expansion_prefix = if item.is_dir {
                 if item.is_expanded { "[-] " } else { "[+] " }
             } else {
                 "    "
             };
-            let full_line = format!(
-                "{}{}{}",
-                expansion_prefix, selection_prefix, item.display_text
-            );
-            ListItem::new(full_line)
+            let state_style = palette.selection_style(item.state);
+            // With the age view on, file names take the age colors; the checkbox keeps
+            // showing the selection state.
+            let name_style = match (app.age_view, item.is_dir) {
+                (AgeView::Off, _) | (_, true) => state_style,
+                _ => match AgeBucket::of(item.modified) {
+                    Some(AgeBucket::Today) => Style::default().fg(palette.age_today),
+                    Some(AgeBucket::ThisWeek) => Style::default().fg(palette.age_this_week),
+                    Some(AgeBucket::Older) | None => Style::default(),
+                },
+            };
+            let mut spans = vec![
+                Span::raw(expansion_prefix),
+                Span::styled(selection_prefix, state_style),
+                Span::styled(item.display_text.as_str(), name_style),
+            ];
+            if app.show_sizes {
+                // Directories show the total size of everything below them.
+                let size = utils::format_size(app.subtree_sizes[item_actual_idx]);
+                // Display width, since icons are two columns wide.
+                let used: usize = spans.iter().map(Span::width).sum();
+                let padding = row_width.saturating_sub(used + size.len());
+                spans.push(Span::raw(" ".repeat(padding.max(1))));
+                spans.push(Span::raw(size));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
-    let list_title = if !app.filter_input.is_empty() && app.mode == AppMode::Normal {
-        format!("Files (Filter: '{}')", app.filter_input)
+    let mut list_title = if !app.filter_input.is_empty() && app.mode == AppMode::Normal {
+        let kind = if app.filter_is_regex {
+            "Regex"
+        } else {
+            "Filter"
+        };
+        format!("Files ({}: '{}')", kind, app.filter_input)
     } else {
         "Select files/directories".to_string()
     };
+    // Counts rows, so it follows filtering as well as expanding and collapsing.
+    list_title.push_str(&format!(
+        " | Showing {} of {}",
+        num_visible_items,
+        app.items.len()
+    ));
+    if app.age_view != AgeView::Off {
+        list_title.push_str(&format!(
+            " | Age: {} ({}: today, {}: this week, plain: older)",
+            app.age_view.label(),
+            Palette::color_name(palette.age_today),
+            Palette::color_name(palette.age_this_week)
+        ));
+    }
 
     let list_widget = List::new(list_items)
-        .block(Block::default().borders(Borders::ALL).title(list_title))
-        .highlight_style(
-            Style::default()
-                .add_modifier(Modifier::BOLD)
-                .bg(Color::DarkGray),
-        )
+        .block(bordered_block(palette).title(list_title))
+        .highlight_style(palette.highlight_style())
         .highlight_symbol("❯ ");
 
     let mut list_state_for_view = ratatui::widgets::ListState::default();
@@ -87,19 +210,215 @@ fn draw_main_list_block(f: &mut Frame, app: &mut TuiApp, area: Rect) {
         .iter()
         .position(|&idx| idx == app.current_selection_idx);
 
-    if let Some(selected_idx_in_visible_list) = current_selected_item_in_visible_list_idx_opt {
-        if selected_idx_in_visible_list >= app.scroll_offset
-            && selected_idx_in_visible_list < app.scroll_offset + app.list_viewport_height
-        {
-            list_state_for_view.select(Some(selected_idx_in_visible_list - app.scroll_offset));
-        }
+    if let Some(selected_idx_in_visible_list) = current_selected_item_in_visible_list_idx_opt
+        && selected_idx_in_visible_list >= app.scroll_offset
+        && selected_idx_in_visible_list < app.scroll_offset + app.list_viewport_height
+    {
+        list_state_for_view.select(Some(selected_idx_in_visible_list - app.scroll_offset));
     }
     f.render_stateful_widget(list_widget, area, &mut list_state_for_view);
 }
 
+fn draw_preview_block(f: &mut Frame, app: &mut TuiApp, palette: &Palette, area: Rect) {
+    let title = app
+        .items
+        .get(app.current_selection_idx)
+        .and_then(|item| item.path.file_name())
+        .map(|name| format!("Preview: {}", name.to_string_lossy()))
+        .unwrap_or_else(|| "Preview".to_string());
+    let max_lines = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = app
+        .current_preview_lines()
+        .iter()
+        .take(max_lines)
+        .cloned()
+        .collect();
+    let preview = Paragraph::new(lines).block(bordered_block(palette).title(title));
+    f.render_widget(preview, area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn draw_fuzzy_overlay(f: &mut Frame, app: &TuiApp, palette: &Palette, area: Rect) {
+    let overlay_area = centered_rect(80, 70, area);
+    f.render_widget(Clear, overlay_area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(overlay_area);
+
+    let input = Paragraph::new(format!("> {}", app.fuzzy_input))
+        .block(bordered_block(palette).title("Find files (Tab: mark, Enter: select, Esc: cancel)"));
+    f.render_widget(input, chunks[0]);
+    f.set_cursor_position((
+        chunks[0].x + 3 + app.fuzzy_input.chars().count() as u16,
+        chunks[0].y + 1,
+    ));
+
+    let ranked = app.fuzzy_ranked_file_indices();
+    let results_height = chunks[1].height.saturating_sub(2) as usize;
+    // Keep the cursor row on screen by scrolling the window of results.
+    let first_shown = app
+        .fuzzy_cursor
+        .saturating_sub(results_height.saturating_sub(1));
+    let result_items: Vec<ListItem> = ranked
+        .iter()
+        .skip(first_shown)
+        .take(results_height)
+        .map(|&item_idx| {
+            let marker = if app.fuzzy_marked.contains(&item_idx) {
+                "[x] "
+            } else {
+                "[ ] "
+            };
+            ListItem::new(format!("{}{}", marker, app.relative_path_of(item_idx)))
+        })
+        .collect();
+    let results = List::new(result_items)
+        .block(bordered_block(palette).title(format!("{} matches", ranked.len())))
+        .highlight_style(palette.highlight_style())
+        .highlight_symbol("❯ ");
+    let mut results_state = ratatui::widgets::ListState::default();
+    if !ranked.is_empty() {
+        results_state.select(Some(app.fuzzy_cursor - first_shown));
+    }
+    f.render_stateful_widget(results, chunks[1], &mut results_state);
+}
+
+fn draw_help_overlay(f: &mut Frame, app: &mut TuiApp, palette: &Palette, area: Rect) {
+    f.render_widget(Clear, area);
+    let key_width = KEYBINDINGS
+        .iter()
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Line> = KEYBINDINGS
+        .iter()
+        .map(|(keys, description)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("  {:<width$}  ", keys, width = key_width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(*description),
+            ])
+        })
+        .collect();
+    // Clamp here, where the overlay height is known, so scrolling stops at the last line.
+    let inner_height = area.height.saturating_sub(2) as usize;
+    app.help_scroll = app
+        .help_scroll
+        .min(lines.len().saturating_sub(inner_height));
+    let help = Paragraph::new(lines)
+        .block(bordered_block(palette).title("Keybindings (j/k, PgUp/PgDn: scroll | ?/Esc: close)"))
+        .scroll((app.help_scroll as u16, 0));
+    f.render_widget(help, area);
+}
+
+fn draw_grep_overlay(f: &mut Frame, app: &TuiApp, palette: &Palette, area: Rect) {
+    let overlay_area = centered_rect(80, 70, area);
+    f.render_widget(Clear, overlay_area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(overlay_area);
+
+    let input = Paragraph::new(format!("> {}", app.grep_input)).block(
+        bordered_block(palette)
+            .title("Search file contents (Enter: search/select, Tab: mark, Esc: cancel)"),
+    );
+    f.render_widget(input, chunks[0]);
+    f.set_cursor_position((
+        chunks[0].x + 3 + app.grep_input.chars().count() as u16,
+        chunks[0].y + 1,
+    ));
+
+    let Some(results) = app.grep_results.as_ref() else {
+        let hint = Paragraph::new("Press Enter to search.")
+            .block(bordered_block(palette).title("Results"));
+        f.render_widget(hint, chunks[1]);
+        return;
+    };
+    let results_height = chunks[1].height.saturating_sub(2) as usize;
+    let first_shown = app
+        .grep_cursor
+        .saturating_sub(results_height.saturating_sub(1));
+    let result_items: Vec<ListItem> = results
+        .iter()
+        .skip(first_shown)
+        .take(results_height)
+        .map(|&(item_idx, matching_lines)| {
+            let marker = if app.grep_marked.contains(&item_idx) {
+                "[x] "
+            } else {
+                "[ ] "
+            };
+            ListItem::new(format!(
+                "{}{} ({} matching lines)",
+                marker,
+                app.relative_path_of(item_idx),
+                matching_lines
+            ))
+        })
+        .collect();
+    let list = List::new(result_items)
+        .block(bordered_block(palette).title(format!("{} files", results.len())))
+        .highlight_style(palette.highlight_style())
+        .highlight_symbol("❯ ");
+    let mut results_state = ratatui::widgets::ListState::default();
+    if !results.is_empty() {
+        results_state.select(Some(app.grep_cursor - first_shown));
+    }
+    f.render_stateful_widget(list, chunks[1], &mut results_state);
+}
+
+fn draw_status_line(f: &mut Frame, app: &TuiApp, area: Rect) {
+    // File contents aren't loaded yet, so estimate tokens from bytes on disk.
+    let (selected_files, selected_bytes) = app.selected_files_summary();
+    let mut status = format!(
+        " Selected: {} files, ≈ {} tokens",
+        selected_files,
+        utils::format_token_count((selected_bytes / 4) as usize)
+    );
+    if let Some(delta) = app.toggle_token_delta() {
+        status.push_str(&format!(
+            " | Toggle: {}{}",
+            if delta < 0 { '-' } else { '+' },
+            utils::format_token_count(delta.unsigned_abs() as usize)
+        ));
+    }
+    if let Some(count) = app.pending_count {
+        status.push_str(&format!(" | Go to: {}G", count));
+    }
+    if let Some(message) = &app.status_message {
+        status.push_str(&format!(" | {}", message));
+    }
+    f.render_widget(Paragraph::new(status), area);
+}
+
 pub(super) fn ui_frame(frame: &mut Frame, app: &mut TuiApp) {
+    let palette = app.palette;
     let help_lines = 2;
-    let filter_input_height = if app.mode == AppMode::Filtering { 3 } else { 0 };
+    // The filter and the extension prompt share the input row below the help block.
+    let has_input_row = matches!(app.mode, AppMode::Filtering | AppMode::ExtensionPrompt);
+    let filter_input_height = if has_input_row { 3 } else { 0 };
     let top_block_container_height = (help_lines + 2) + filter_input_height;
 
     let main_chunks = Layout::default()
@@ -108,13 +427,15 @@ pub(super) fn ui_frame(frame: &mut Frame, app: &mut TuiApp) {
         .constraints([
             Constraint::Length(top_block_container_height),
             Constraint::Min(0),
+            Constraint::Length(1),
         ])
         .split(frame.area());
 
     let top_container_area = main_chunks[0];
     let list_area = main_chunks[1];
+    let status_area = main_chunks[2];
 
-    let top_content_constraints = if app.mode == AppMode::Filtering {
+    let top_content_constraints = if has_input_row {
         vec![
             Constraint::Length(help_lines + 2),
             Constraint::Length(filter_input_height),
@@ -127,10 +448,34 @@ pub(super) fn ui_frame(frame: &mut Frame, app: &mut TuiApp) {
         .constraints(top_content_constraints)
         .split(top_container_area);
 
-    draw_help_block(frame, app, top_content_chunks[0]);
-    if app.mode == AppMode::Filtering {
-        draw_filter_input_block(frame, app, top_content_chunks[1]);
+    draw_help_block(frame, app, &palette, top_content_chunks[0]);
+    match app.mode {
+        AppMode::Filtering => draw_filter_input_block(frame, app, &palette, top_content_chunks[1]),
+        AppMode::ExtensionPrompt => {
+            draw_extension_prompt_block(frame, app, &palette, top_content_chunks[1])
+        }
+        _ => {}
+    }
+
+    if app.show_preview {
+        let list_and_preview = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(list_area);
+        draw_main_list_block(frame, app, &palette, list_and_preview[0]);
+        draw_preview_block(frame, app, &palette, list_and_preview[1]);
+    } else {
+        draw_main_list_block(frame, app, &palette, list_area);
     }
+    draw_status_line(frame, app, status_area);
 
-    draw_main_list_block(frame, app, list_area);
+    if app.mode == AppMode::Fuzzy {
+        draw_fuzzy_overlay(frame, app, &palette, frame.area());
+    }
+    if app.mode == AppMode::Grep {
+        draw_grep_overlay(frame, app, &palette, frame.area());
+    }
+    if app.show_help {
+        draw_help_overlay(frame, app, &palette, frame.area());
+    }
 }
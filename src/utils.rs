@@ -1,4 +1,247 @@
+use std::path::Path;
+
 /// Rough estimate: GPT-style token ≈ 4 chars (good enough for UI)
 pub fn approx_tokens(s: &str) -> usize {
-    s.chars().count() / 4
+    approx_tokens_for_chars(s.chars().count())
+}
+
+/// `approx_tokens` for a text of `chars` characters.
+pub fn approx_tokens_for_chars(chars: usize) -> usize {
+    chars / 4
+}
+
+/// Compact token count for display, e.g. `850` or `1.2k`.
+pub fn format_token_count(tokens: usize) -> String {
+    if tokens < 1000 {
+        tokens.to_string()
+    } else {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    }
+}
+
+/// Compact byte size for display, e.g. `512B`, `4.2K` or `1.1M`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", size, UNITS[unit])
+}
+
+/// Lowercased extension of `path` without the dot, or an empty string if it has none.
+pub fn extension_key(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Human-readable language name for a lowercased file extension, if it's a known one.
+pub fn language_for_extension(ext: &str) -> Option<&'static str> {
+    let language = match ext {
+        "rs" => "Rust",
+        "py" | "pyi" => "Python",
+        "js" | "mjs" | "cjs" => "JavaScript",
+        "jsx" => "JavaScript (JSX)",
+        "ts" | "mts" | "cts" => "TypeScript",
+        "tsx" => "TypeScript (TSX)",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "scala" => "Scala",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "C++",
+        "cs" => "C#",
+        "swift" => "Swift",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "lua" => "Lua",
+        "sh" | "bash" | "zsh" => "Shell",
+        "ps1" => "PowerShell",
+        "sql" => "SQL",
+        "html" | "htm" => "HTML",
+        "css" => "CSS",
+        "scss" | "sass" => "Sass",
+        "md" | "markdown" => "Markdown",
+        "json" => "JSON",
+        "toml" => "TOML",
+        "yaml" | "yml" => "YAML",
+        "xml" => "XML",
+        "txt" => "Text",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Emoji shown before a tree entry with `--icons`. Only emoji that render two columns wide
+/// by default are used, so the tree stays aligned.
+pub fn file_icon(path: &Path, is_dir: bool) -> &'static str {
+    if is_dir {
+        return "📁";
+    }
+    match extension_key(path).as_str() {
+        "rs" => "🦀",
+        "py" | "pyi" => "🐍",
+        "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx" => "📜",
+        "go" => "🐹",
+        "java" | "kt" | "kts" | "scala" => "☕",
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" | "cs" => "🔩",
+        "swift" => "🐦",
+        "rb" => "💎",
+        "sh" | "bash" | "zsh" | "ps1" => "🐚",
+        "html" | "htm" | "xml" => "🌐",
+        "css" | "scss" | "sass" => "🎨",
+        "md" | "markdown" | "txt" | "rst" => "📝",
+        "json" | "toml" | "yaml" | "yml" | "ini" | "cfg" => "🔧",
+        "lock" => "🔒",
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "ico" => "📷",
+        "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" => "📦",
+        _ => "📄",
+    }
+}
+
+/// Heuristic binary check (as used by git): a NUL byte within the first 8000 bytes.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8000).any(|&b| b == 0)
+}
+
+/// Collapses every run of blank (whitespace-only) lines into a single empty line.
+pub fn collapse_blank_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        let is_blank = line.trim().is_empty();
+        if is_blank && lines.last().is_some_and(|previous| previous.is_empty()) {
+            continue;
+        }
+        lines.push(if is_blank { "" } else { line });
+    }
+    lines.join("\n")
+}
+
+/// Cuts every line longer than `max_chars` characters, appending ` … [truncated M chars]`.
+/// Returns None when no line is that long.
+pub fn truncate_long_lines(text: &str, max_chars: usize) -> Option<String> {
+    let mut truncated_any = false;
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| match line.char_indices().nth(max_chars) {
+            Some((cut, _)) => {
+                truncated_any = true;
+                let dropped = line[cut..].chars().count();
+                format!("{} … [truncated {} chars]", &line[..cut], dropped)
+            }
+            None => line.to_string(),
+        })
+        .collect();
+    truncated_any.then(|| lines.join("\n"))
+}
+
+/// Keeps the first `max_lines` lines, followed by `... [M more lines]`. Trailing blank lines
+/// are not counted, since they are trimmed from the output anyway. None if nothing is cut.
+pub fn head_lines(text: &str, max_lines: usize) -> Option<String> {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    if lines.len() <= max_lines {
+        return None;
+    }
+    let mut kept = lines[..max_lines].join("\n");
+    if !kept.is_empty() {
+        kept.push('\n');
+    }
+    kept.push_str(&format!("... [{} more lines]", lines.len() - max_lines));
+    Some(kept)
+}
+
+/// Parses a duration such as `30m`, `2h`, `3d` or `1w` (also `s` for seconds), as taken by
+/// `--newer-than`.
+pub fn parse_duration(text: &str) -> Result<std::time::Duration, String> {
+    let text = text.trim();
+    let unit_start = text
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("'{}' has no unit; use s, m, h, d or w, e.g. 2h", text))?;
+    let (amount, unit) = text.split_at(unit_start);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number, e.g. 2h", text))?;
+    let seconds_per_unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}'; use s, m, h, d or w", unit)),
+    };
+    amount
+        .checked_mul(seconds_per_unit)
+        .map(std::time::Duration::from_secs)
+        .ok_or_else(|| format!("'{}' is too long", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapse_blank_lines_squeezes_runs_to_one_line() {
+        assert_eq!(collapse_blank_lines("a\n\n\n\nb\n\n\nc"), "a\n\nb\n\nc");
+        // A single blank line is left alone.
+        assert_eq!(collapse_blank_lines("a\n\nb"), "a\n\nb");
+    }
+
+    #[test]
+    fn collapse_blank_lines_treats_whitespace_only_lines_as_blank() {
+        assert_eq!(collapse_blank_lines("a\n  \n\t\n \t \nb"), "a\n\nb");
+        // A lone whitespace-only line is emptied too.
+        assert_eq!(collapse_blank_lines("a\n   \nb"), "a\n\nb");
+    }
+
+    #[test]
+    fn collapse_blank_lines_keeps_one_leading_and_trailing_blank_line() {
+        assert_eq!(collapse_blank_lines("\n\n\na"), "\na");
+        assert_eq!(collapse_blank_lines("a\n\n\n\n"), "a\n");
+        assert_eq!(collapse_blank_lines("\n \n\n"), "");
+    }
+
+    #[test]
+    fn languages_are_looked_up_by_lowercased_extension() {
+        let language_of = |path: &str| language_for_extension(&extension_key(Path::new(path)));
+        assert_eq!(language_of("src/main.rs"), Some("Rust"));
+        assert_eq!(language_of("App.TSX"), Some("TypeScript (TSX)"));
+        assert_eq!(language_of("lib.hpp"), Some("C++"));
+        assert_eq!(language_of("config.yml"), Some("YAML"));
+        assert_eq!(language_of("data.weird"), None);
+        assert_eq!(language_of("Makefile"), None);
+    }
+
+    #[test]
+    fn head_lines_with_zero_keeps_only_the_marker() {
+        assert_eq!(
+            head_lines("a\nb\nc", 0).as_deref(),
+            Some("... [3 more lines]")
+        );
+    }
+
+    #[test]
+    fn head_lines_cuts_nothing_at_or_below_the_line_count() {
+        assert_eq!(head_lines("a\nb\nc", 3), None);
+        assert_eq!(head_lines("a\nb\nc", 10), None);
+        // Trailing blank lines are not counted.
+        assert_eq!(head_lines("a\nb\nc\n\n\n", 3), None);
+    }
+
+    #[test]
+    fn head_lines_counts_the_dropped_lines() {
+        assert_eq!(
+            head_lines("a\nb\nc\nd\ne\n", 2).as_deref(),
+            Some("a\nb\n... [3 more lines]")
+        );
+        assert_eq!(
+            head_lines("a\nb\nc", 2).as_deref(),
+            Some("a\nb\n... [1 more lines]")
+        );
+    }
 }
@@ -1,12 +1,61 @@
-use crate::{cli, clipboard, file_scanner, tree_builder, tui, utils};
-use anyhow::Result;
+use crate::{
+    cli, clipboard, comment_stripper, config, file_directives::FileDirectives, file_scanner, git,
+    redact, selection_store, template, tree_builder, tui, utils,
+};
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
 use glob::Pattern;
+use rayon::prelude::*;
 use std::{
-    collections::{HashMap, HashSet},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     fs,
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
 };
 
+/// Outcomes that end a run with their own stable exit status, next to 0 (success) and 1
+/// (any other error). They are returned as errors, after the user has been told what
+/// happened, so `main` is the one place that turns them into an exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Nothing matched, or nothing was selected to yank.
+    NothingToYank,
+    /// The TUI was quit without confirming a selection.
+    Cancelled,
+    /// The output could not be copied to the clipboard.
+    ClipboardFailed,
+    /// The output was over --max-tokens with --max-tokens-strict, so it was not delivered.
+    OverTokenBudget,
+}
+
+impl Exit {
+    pub fn code(self) -> i32 {
+        match self {
+            Exit::NothingToYank => 2,
+            Exit::Cancelled => 3,
+            Exit::ClipboardFailed => 4,
+            Exit::OverTokenBudget => 5,
+        }
+    }
+}
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Exit::NothingToYank => "nothing to yank",
+            Exit::Cancelled => "selection cancelled",
+            Exit::ClipboardFailed => "could not copy to the clipboard",
+            Exit::OverTokenBudget => "output over --max-tokens",
+        })
+    }
+}
+
+impl std::error::Error for Exit {}
+
+// Pattern used when no PATTERN remains after taking the scan root from the arguments.
+const DEFAULT_PATTERN: &str = "**/*";
+
 // Helper function to determine the effective root directory for scanning and the glob patterns to apply.
 // Handles CLI arguments for patterns and deriving the scan root.
 fn determine_scan_configuration(cli_args: &cli::Cli) -> Result<(PathBuf, Vec<Pattern>)> {
@@ -14,7 +63,7 @@ fn determine_scan_configuration(cli_args: &cli::Cli) -> Result<(PathBuf, Vec<Pat
     let mut actual_patterns_str: Vec<String> = cli_args.patterns.clone();
 
     // If the first positional argument is a directory, use it as the scan_root.
-    if let Some(first_pattern_str) = cli_args.patterns.get(0) {
+    if let Some(first_pattern_str) = cli_args.patterns.first() {
         let potential_root_path = PathBuf::from(first_pattern_str);
         if potential_root_path.is_dir() {
             scan_root = potential_root_path
@@ -25,9 +74,11 @@ fn determine_scan_configuration(cli_args: &cli::Cli) -> Result<(PathBuf, Vec<Pat
         }
     }
 
-    // If no patterns are left (or none were provided initially aside from a possible root), default to "**/*".
+    // If no patterns are left (or none were provided initially aside from a possible root), default
+    // to DEFAULT_PATTERN under the scan root. `repoyank -t rs src` therefore means "every .rs file
+    // under src": the pattern matches everything and --type alone decides which files are kept.
     if actual_patterns_str.is_empty() {
-        actual_patterns_str.push("**/*".to_string());
+        actual_patterns_str.push(DEFAULT_PATTERN.to_string());
     }
 
     // Compile string patterns into glob::Pattern objects.
@@ -45,25 +96,129 @@ fn determine_scan_configuration(cli_args: &cli::Cli) -> Result<(PathBuf, Vec<Pat
     Ok((scan_root, glob_filter_patterns))
 }
 
-// Scans for files and directories based on scan_root and applies --type filter,
-// then further filters based on the primary glob patterns.
+// Compiles the globs given to `flag`, failing on the first invalid one.
+fn glob_patterns(globs: &[String], flag: &str) -> Result<Vec<Pattern>> {
+    globs
+        .iter()
+        .map(|glob| Pattern::new(glob).with_context(|| format!("Invalid {} glob '{}'", flag, glob)))
+        .collect()
+}
+
+// The entries of a --from-file list: globs, and files given by path.
+#[derive(Default)]
+struct FileList {
+    patterns: Vec<Pattern>,
+    files: Vec<PathBuf>,
+}
+
+// Reads the newline-separated paths and globs of --from-file (`-` for stdin). Paths are
+// relative to `scan_root`; a listed directory stands for every file below it. Listed paths
+// that do not exist are skipped with a warning.
+fn read_file_list(source: &Path, scan_root: &Path) -> Result<FileList> {
+    let text = if source == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())
+            .map_err(|e| anyhow!("Could not read --from-file list from stdin: {}", e))?
+    } else {
+        fs::read_to_string(source).map_err(|e| {
+            anyhow!(
+                "Could not read --from-file list {}: {}",
+                source.display(),
+                e
+            )
+        })?
+    };
+    let canonical_root = scan_root.canonicalize().ok();
+
+    let mut list = FileList::default();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if line.contains(['*', '?', '[']) {
+            match Pattern::new(line.trim_start_matches("./")) {
+                Ok(pattern) => list.patterns.push(pattern),
+                Err(e) => eprintln!("⚠️ Warning: Invalid glob '{}' in --from-file: {}", line, e),
+            }
+            continue;
+        }
+        let listed = Path::new(line);
+        let relative_path = if listed.is_absolute() {
+            match canonical_root
+                .as_deref()
+                .and_then(|root| listed.strip_prefix(root).ok())
+            {
+                Some(below_root) => below_root,
+                None => {
+                    eprintln!(
+                        "⚠️ Warning: {} from --from-file is outside the scan root; skipping.",
+                        line
+                    );
+                    continue;
+                }
+            }
+        } else {
+            listed.strip_prefix(".").unwrap_or(listed)
+        };
+        let path = scan_root.join(relative_path);
+        if path.is_file() {
+            list.files.push(path);
+        } else if path.is_dir() {
+            let below = Pattern::escape(&relative_path.to_string_lossy());
+            let glob = if below.is_empty() {
+                DEFAULT_PATTERN.to_string()
+            } else {
+                format!("{}/**/*", below)
+            };
+            list.patterns
+                .push(Pattern::new(&glob).expect("escaped directory glob is valid"));
+        } else {
+            eprintln!(
+                "⚠️ Warning: {} from --from-file does not exist; skipping.",
+                line
+            );
+        }
+    }
+    Ok(list)
+}
+
+// Scans for files and directories based on scan_root and applies the --type filters,
+// then further filters based on the primary glob patterns. `listed_files` (--from-file)
+// are added without walking, subject to the same filters.
 fn gather_initial_candidates(
     scan_root: &Path,
-    type_filter: &[String],
-    include_ignored: bool,
+    scan_options: &file_scanner::ScanOptions,
     glob_filter_patterns: &[Pattern],
-) -> Result<Vec<(PathBuf, bool)>> {
-    // Initial broad scan respecting --type and --include-ignored.
-    let all_found_items_from_scan =
-        file_scanner::scan_files(scan_root, type_filter, include_ignored)?;
+    listed_files: &[PathBuf],
+    only_files: Option<&HashSet<PathBuf>>,
+    cli_args: &cli::Cli,
+) -> Result<Vec<file_scanner::ScannedEntry>> {
+    // Initial broad scan respecting --type, --exclude-type and the walk options. Without
+    // patterns nothing could match, so a --from-file list of plain paths skips the walk.
+    let all_found_items_from_scan = if glob_filter_patterns.is_empty() {
+        Vec::new()
+    } else {
+        file_scanner::scan_files(
+            scan_root,
+            &cli_args.type_filter,
+            &cli_args.exclude_types,
+            scan_options,
+        )?
+    };
 
     // Filter the broad scan results using the primary glob patterns.
-    let mut initial_scan_results: Vec<(PathBuf, bool)> = all_found_items_from_scan
+    let mut initial_scan_results: Vec<file_scanner::ScannedEntry> = all_found_items_from_scan
         .into_iter()
-        .filter(|(path, is_dir)| {
-            if *is_dir {
+        // --min-file-size: empty files always go, smaller ones too. Uses the walk's sizes.
+        .filter(|entry| {
+            entry.is_dir
+                || cli_args
+                    .min_file_size
+                    .is_none_or(|min_size| entry.size > 0 && entry.size >= min_size)
+        })
+        .filter(|entry| {
+            let path = &entry.path;
+            if entry.is_dir {
                 // Directories are kept for now; their relevance is determined later.
                 true
+            } else if only_files.is_some_and(|only| !only.contains(path)) {
+                false // Not among the files reported by git (--git-modified/--git-diff/--staged).
             } else {
                 // For files, check if they match any of the glob patterns relative to scan_root.
                 if let Ok(relative_path) = path.strip_prefix(scan_root) {
@@ -86,41 +241,127 @@ fn gather_initial_candidates(
         })
         .collect();
 
+    for file in listed_files {
+        if !file_scanner::matches_type_filters(file, &cli_args.type_filter, &cli_args.exclude_types)
+            || only_files.is_some_and(|only| !only.contains(file))
+        {
+            continue;
+        }
+        // One stat gives the size for --min-file-size and the size and time for --sort.
+        let metadata = fs::metadata(file).ok();
+        let size = metadata.as_ref().map_or(0, |m| m.len());
+        if cli_args
+            .min_file_size
+            .is_some_and(|min_size| metadata.is_none() || size == 0 || size < min_size)
+        {
+            continue;
+        }
+        initial_scan_results.push(file_scanner::ScannedEntry {
+            path: file.clone(),
+            is_dir: false,
+            size,
+            modified: metadata.and_then(|m| m.modified().ok()),
+            is_symlink: false,
+        });
+        // The walk would have supplied the directories above the file.
+        for ancestor in file.ancestors().skip(1) {
+            if ancestor == scan_root || !ancestor.starts_with(scan_root) {
+                break;
+            }
+            initial_scan_results.push(file_scanner::ScannedEntry::directory(
+                ancestor.to_path_buf(),
+            ));
+        }
+    }
+
     // Ensure scan_root itself is included in results if it's a directory and relevant.
-    if !initial_scan_results.iter().any(|(p, _)| p == scan_root) && scan_root.is_dir() {
+    if !initial_scan_results
+        .iter()
+        .any(|entry| entry.path == scan_root)
+        && scan_root.is_dir()
+    {
         let root_explicitly_matched_or_implied = glob_filter_patterns.iter().any(|p| {
             p.matches_path(Path::new("."))
-                || p.as_str() == "**/*"
+                || p.as_str() == DEFAULT_PATTERN
                 || (scan_root
                     .file_name()
-                    .map_or(false, |name| p.matches_path(Path::new(name))))
+                    .is_some_and(|name| p.matches_path(Path::new(name))))
         });
         let has_children_in_results = initial_scan_results
             .iter()
-            .any(|(p, _)| p.starts_with(scan_root) && p != scan_root);
+            .any(|entry| entry.path.starts_with(scan_root) && entry.path != scan_root);
 
         if root_explicitly_matched_or_implied || has_children_in_results {
-            initial_scan_results.push((scan_root.to_path_buf(), true));
+            initial_scan_results.push(file_scanner::ScannedEntry::directory(
+                scan_root.to_path_buf(),
+            ));
         }
     }
 
     // Sort for consistent processing and display.
-    initial_scan_results.sort_by(|(a, _), (b, _)| a.cmp(b));
-    initial_scan_results.dedup_by(|(a, _), (b, _)| a == b);
+    tree_builder::sort_tree_entries(
+        &mut initial_scan_results,
+        cli_args.sort,
+        cli_args.dirs_first,
+    );
+    initial_scan_results.dedup_by(|a, b| a.path == b.path);
+
+    // The same real file can show up under several logical paths (e.g. a symlink pointing back
+    // into the tree). Dedup files by canonical identity, keeping the first-seen logical path.
+    // Only a symlink, or a listed path that was not walked, can alias another, so walked
+    // files are identified by their place under the canonical root without a syscall each.
+    let canonical_root = scan_root
+        .canonicalize()
+        .unwrap_or_else(|_| scan_root.to_path_buf());
+    let listed: HashSet<&PathBuf> = listed_files.iter().collect();
+    let mut seen_canonical_files = HashSet::new();
+    initial_scan_results.retain(|entry| {
+        if entry.is_dir {
+            return true;
+        }
+        let identity = match entry.path.strip_prefix(scan_root) {
+            Ok(relative)
+                if !entry.is_symlink
+                    && !scan_options.follow_symlinks
+                    && !listed.contains(&entry.path) =>
+            {
+                canonical_root.join(relative)
+            }
+            _ => entry
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| entry.path.clone()),
+        };
+        seen_canonical_files.insert(identity)
+    });
+
+    // Guard against yanking a whole home directory by accident (--max-files 0 disables).
+    let candidate_files = initial_scan_results
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .count();
+    let max_files = cli_args.max_files;
+    if max_files > 0 && candidate_files > max_files {
+        return Err(anyhow!(
+            "{} files match, more than --max-files {}. Narrow the selection with PATTERNs or --type, or raise --max-files (0 disables the limit).",
+            candidate_files,
+            max_files
+        ));
+    }
 
     Ok(initial_scan_results)
 }
 
 // Handles the --all (headless) mode: directly selects files and prepares data for output.
 fn run_headless_mode(
-    initial_scan_results: &[(PathBuf, bool)], // Already filtered candidates
+    initial_scan_results: &[file_scanner::ScannedEntry], // Already filtered candidates
     scan_root: &Path,
 ) -> Result<(Vec<tui::SelectableItem>, Vec<PathBuf>)> {
     // All non-directory items from the candidates are considered for yanking.
     let mut files_to_yank: Vec<PathBuf> = initial_scan_results
         .iter()
-        .filter(|(_, is_dir)| !*is_dir)
-        .map(|(path, _)| path.clone())
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path.clone())
         .collect();
 
     files_to_yank.sort();
@@ -149,55 +390,104 @@ fn run_headless_mode(
         }
     }
 
-    let path_to_is_dir_map: HashMap<PathBuf, bool> = initial_scan_results.iter().cloned().collect();
-
-    let mut temp_final_items_for_tree: Vec<(PathBuf, bool)> = items_for_tree_building_set
-        .into_iter()
-        .map(|p| {
-            (
-                p.clone(),
-                path_to_is_dir_map
-                    .get(&p)
-                    .copied()
-                    .unwrap_or_else(|| p.is_dir()),
-            )
-        })
+    let path_to_entry_map: HashMap<&Path, &file_scanner::ScannedEntry> = initial_scan_results
+        .iter()
+        .map(|entry| (entry.path.as_path(), entry))
         .collect();
-    temp_final_items_for_tree.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-    // Create SelectableItem structs for tree generation.
+    let mut temp_final_items_for_tree: Vec<file_scanner::ScannedEntry> =
+        items_for_tree_building_set
+            .into_iter()
+            .map(|p| match path_to_entry_map.get(p.as_path()) {
+                Some(entry) => (*entry).clone(),
+                // Only ancestors of yanked files are missing from the candidates.
+                None => file_scanner::ScannedEntry::directory(p),
+            })
+            .collect();
+    temp_final_items_for_tree.sort_by(|a, b| a.path.cmp(&b.path));
+
+    // Create SelectableItem structs for tree generation, with the walk's sizes and times.
     let final_tui_items_for_tree = temp_final_items_for_tree
-        .iter()
-        .map(|(path, is_dir)| tui::SelectableItem {
-            path: path.clone(),
-            display_text: "".to_string(),
-            is_dir: *is_dir,
-            is_expanded: true,
-            state: if !*is_dir && files_to_yank.contains(path) {
+        .into_iter()
+        .map(|entry| tui::SelectableItem {
+            state: if !entry.is_dir && files_to_yank.binary_search(&entry.path).is_ok() {
                 tui::SelectionState::FullySelected
-            } else if *is_dir {
+            } else if entry.is_dir {
                 tui::SelectionState::PartiallySelected
             } else {
                 tui::SelectionState::NotSelected
             },
+            path: entry.path,
+            display_text: "".to_string(),
+            is_dir: entry.is_dir,
+            is_expanded: true,
             children_indices: vec![],
             parent_index: None,
+            modified: entry.modified,
+            size: entry.size,
         })
         .collect();
 
     Ok((final_tui_items_for_tree, files_to_yank))
 }
 
+// Indices of the files modified within `window` before `now` (--newer-than). Files whose
+// mtime could not be read are left out; a future mtime counts as recent.
+fn modified_within(
+    items: &[tui::SelectableItem],
+    window: std::time::Duration,
+    now: std::time::SystemTime,
+) -> Vec<usize> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| {
+            !item.is_dir
+                && item.modified.is_some_and(|modified| {
+                    now.duration_since(modified)
+                        .map_or(true, |age| age <= window)
+                })
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+// Marks the given items FullySelected and recomputes their ancestors' states.
+fn preselect_items(items: &mut [tui::SelectableItem], matched_item_indices: &[usize]) {
+    for &item_idx in matched_item_indices {
+        tui::apply_state_and_propagate_down_vec(
+            items,
+            item_idx,
+            tui::SelectionState::FullySelected,
+        );
+    }
+    for &item_idx in matched_item_indices {
+        tui::update_all_parent_states_from_child_vec(items, item_idx);
+    }
+}
+
+// How an interactive TUI session ended.
+enum InteractiveOutcome {
+    // Confirmed: the items for the tree and the files to yank.
+    Confirmed(Vec<tui::SelectableItem>, Vec<PathBuf>),
+    // The user quit without confirming.
+    Cancelled,
+    // There was nothing to show, so the TUI never opened.
+    NothingToSelect,
+}
+
 // Handles interactive TUI mode: prepares data for TUI, runs TUI, processes selections.
 fn run_interactive_mode(
-    initial_scan_results: &[(PathBuf, bool)],
+    initial_scan_results: &[file_scanner::ScannedEntry],
     cli_args: &cli::Cli,
     scan_root: &Path,
-) -> Result<Option<(Vec<tui::SelectableItem>, Vec<PathBuf>)>> {
+    template: Option<&template::Template>,
+) -> Result<InteractiveOutcome> {
     // Determine paths to show in TUI: files from initial_scan_results and their ancestors.
     let mut paths_for_tui_display_set = HashSet::new();
-    for (path, is_dir) in initial_scan_results {
-        if !*is_dir {
+    for entry in initial_scan_results {
+        let path = &entry.path;
+        if !entry.is_dir {
             paths_for_tui_display_set.insert(path.clone());
             let mut current_ancestor = path.parent();
             while let Some(ancestor_path) = current_ancestor {
@@ -222,16 +512,19 @@ fn run_interactive_mode(
         paths_for_tui_display_set.insert(scan_root.to_path_buf());
     }
 
-    let path_to_is_dir_map: HashMap<PathBuf, bool> = initial_scan_results.iter().cloned().collect();
-    let mut selectable_paths_for_tui: Vec<(PathBuf, bool)> = paths_for_tui_display_set
+    let path_to_entry_map: HashMap<&Path, &file_scanner::ScannedEntry> = initial_scan_results
+        .iter()
+        .map(|entry| (entry.path.as_path(), entry))
+        .collect();
+    let mut selectable_entries_for_tui: Vec<file_scanner::ScannedEntry> = paths_for_tui_display_set
         .into_iter()
         .filter_map(|path| {
-            path_to_is_dir_map
-                .get(&path)
-                .map(|is_dir| (path.clone(), *is_dir))
+            path_to_entry_map
+                .get(path.as_path())
+                .map(|entry| (*entry).clone())
                 .or_else(|| {
                     if path == scan_root && scan_root.is_dir() {
-                        Some((path.clone(), true))
+                        Some(file_scanner::ScannedEntry::directory(path.clone()))
                     } else {
                         None
                     }
@@ -239,69 +532,101 @@ fn run_interactive_mode(
         })
         .collect();
 
-    selectable_paths_for_tui.sort_by(|(a, _), (b, _)| a.cmp(b));
-    selectable_paths_for_tui.dedup_by(|(a, _), (b, _)| a == b);
+    tree_builder::sort_tree_entries(
+        &mut selectable_entries_for_tui,
+        cli_args.sort,
+        cli_args.dirs_first,
+    );
+    selectable_entries_for_tui.dedup_by(|a, b| a.path == b.path);
 
-    if selectable_paths_for_tui.is_empty() {
-        return Ok(None); // No items to display in TUI.
+    if selectable_entries_for_tui.is_empty() {
+        return Ok(InteractiveOutcome::NothingToSelect);
     }
 
     // Prepare items for the TUI display.
-    let display_labels = tree_builder::build_tree_labels(&selectable_paths_for_tui, scan_root);
+    let selectable_paths_for_tui: Vec<(PathBuf, bool)> = selectable_entries_for_tui
+        .iter()
+        .map(|entry| (entry.path.clone(), entry.is_dir))
+        .collect();
+    let display_labels =
+        tree_builder::build_tree_labels(&selectable_paths_for_tui, scan_root, cli_args.icons);
     let mut prepared_tui_items =
-        tui::prepare_selectable_items(&selectable_paths_for_tui, &display_labels, scan_root);
+        tui::prepare_selectable_items(&selectable_entries_for_tui, &display_labels, scan_root);
 
     // Apply --select globs for pre-selection in TUI.
     if !cli_args.select_globs.is_empty() {
-        let preselect_glob_patterns: Vec<Pattern> = cli_args
-            .select_globs
-            .iter()
-            .filter_map(|s| match Pattern::new(s) {
-                Ok(p) => Some(p),
-                Err(e) => {
-                    eprintln!("⚠️ Warning: Invalid --select glob pattern '{}': {}", s, e);
-                    std::process::exit(1);
-                }
-            })
-            .collect();
+        let preselect_glob_patterns = glob_patterns(&cli_args.select_globs, "--select")?;
 
         if !preselect_glob_patterns.is_empty() {
             let mut matched_item_indices = Vec::new();
             for (idx, item) in prepared_tui_items.iter().enumerate() {
-                if !item.is_dir {
-                    if let Ok(relative_path) = item.path.strip_prefix(scan_root) {
-                        let path_to_match = if relative_path.as_os_str().is_empty() {
-                            scan_root
-                                .file_name()
-                                .map(PathBuf::from)
-                                .unwrap_or_else(|| relative_path.to_path_buf())
-                        } else {
-                            relative_path.to_path_buf()
-                        };
-                        if preselect_glob_patterns
-                            .iter()
-                            .any(|p| p.matches_path(&path_to_match))
-                        {
-                            matched_item_indices.push(idx);
-                        }
+                if !item.is_dir
+                    && let Ok(relative_path) = item.path.strip_prefix(scan_root)
+                {
+                    let path_to_match = if relative_path.as_os_str().is_empty() {
+                        scan_root
+                            .file_name()
+                            .map(PathBuf::from)
+                            .unwrap_or_else(|| relative_path.to_path_buf())
+                    } else {
+                        relative_path.to_path_buf()
+                    };
+                    if preselect_glob_patterns
+                        .iter()
+                        .any(|p| p.matches_path(&path_to_match))
+                    {
+                        matched_item_indices.push(idx);
                     }
                 }
             }
-            for &item_idx in &matched_item_indices {
-                tui::apply_state_and_propagate_down_vec(
-                    &mut prepared_tui_items,
-                    item_idx,
-                    tui::SelectionState::FullySelected,
-                );
-            }
-            for &item_idx in &matched_item_indices {
-                tui::update_all_parent_states_from_child_vec(&mut prepared_tui_items, item_idx);
+            preselect_items(&mut prepared_tui_items, &matched_item_indices);
+        }
+    }
+
+    // Apply --newer-than: pre-select files modified within the window.
+    if let Some(window) = cli_args.newer_than {
+        let matched_item_indices =
+            modified_within(&prepared_tui_items, window, std::time::SystemTime::now());
+        preselect_items(&mut prepared_tui_items, &matched_item_indices);
+    }
+
+    // Apply --restore: pre-select the files saved by the last confirmed run.
+    // Saved paths that no longer exist simply match no item.
+    if cli_args.restore {
+        match selection_store::load_last_selection(scan_root) {
+            Ok(saved_paths) => {
+                let saved_paths: HashSet<PathBuf> = saved_paths.into_iter().collect();
+                let matched_item_indices: Vec<usize> = prepared_tui_items
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| !item.is_dir && saved_paths.contains(&item.path))
+                    .map(|(idx, _)| idx)
+                    .collect();
+                preselect_items(&mut prepared_tui_items, &matched_item_indices);
             }
+            Err(e) => eprintln!("⚠️ Warning: Could not load the last selection: {}", e),
         }
     }
 
     // Run the TUI.
-    match tui::run_tui_with_prepared_items(prepared_tui_items, scan_root)? {
+    // The `c` key copies the current selection while the TUI keeps the screen, so nothing
+    // may be printed along the way.
+    let silent_args = cli::Cli {
+        quiet: true,
+        no_warnings: true,
+        ..cli_args.clone()
+    };
+    let mut copy_selection = |items: &[tui::SelectableItem]| {
+        copy_selection_to_clipboard(items, scan_root, &silent_args, template)
+            .unwrap_or_else(|e| format!("Copy failed: {:#}", e))
+    };
+
+    match tui::run_tui_with_prepared_items(
+        prepared_tui_items,
+        scan_root,
+        cli_args.theme,
+        &mut copy_selection,
+    )? {
         Some(final_tui_items_from_tui) => {
             // Process TUI selections.
             let mut files_to_yank_interactive: Vec<PathBuf> = final_tui_items_from_tui
@@ -311,270 +636,1783 @@ fn run_interactive_mode(
                 .collect();
             files_to_yank_interactive.sort();
             files_to_yank_interactive.dedup();
-            Ok(Some((final_tui_items_from_tui, files_to_yank_interactive)))
+            // A dry run only previews, so it does not replace what --restore brings back.
+            if !cli_args.dry_run
+                && let Err(e) =
+                    selection_store::save_last_selection(scan_root, &files_to_yank_interactive)
+            {
+                eprintln!(
+                    "⚠️ Warning: Could not save the selection for --restore: {}",
+                    e
+                );
+            }
+            Ok(InteractiveOutcome::Confirmed(
+                final_tui_items_from_tui,
+                files_to_yank_interactive,
+            ))
         }
-        _ => Ok(None), // TUI cancelled by user.
+        None => Ok(InteractiveOutcome::Cancelled),
     }
 }
 
-// Generates the final output string including the directory tree and file contents.
-fn generate_output_string(
-    final_tui_items_for_tree: &[tui::SelectableItem],
-    files_to_yank: &[PathBuf],
-    scan_root: &Path,
-    all_paths_is_dir_map: &HashMap<PathBuf, bool>,
-) -> Result<(Vec<String>, String)> {
-    // Determine nodes for the output tree display.
-    let mut final_tree_node_paths_set = HashSet::new();
-    if scan_root.exists() && scan_root.is_dir() {
-        final_tree_node_paths_set.insert(scan_root.to_path_buf());
+// A yanked file's content as it will be emitted.
+enum FileBody {
+    Text(String),
+    // Base64 of the raw bytes, or None when the file is above --max-binary-bytes.
+    Binary { encoded: Option<String>, size: u64 },
+}
+
+impl FileBody {
+    // The text that ends up in the output, used for per-file token counts.
+    fn emitted_text(&self) -> &str {
+        match self {
+            FileBody::Text(contents) => contents.trim_end(),
+            FileBody::Binary { encoded, .. } => encoded.as_deref().unwrap_or(""),
+        }
     }
+}
 
-    // Add selected/partially selected items and their ancestors from TUI/headless structured items.
-    for item in final_tui_items_for_tree {
-        if item.state == tui::SelectionState::FullySelected
-            || item.state == tui::SelectionState::PartiallySelected
-        {
-            final_tree_node_paths_set.insert(item.path.clone());
-            let mut current_ancestor = item.path.parent();
-            while let Some(ancestor_path) = current_ancestor {
-                if ancestor_path.starts_with(scan_root) || ancestor_path == scan_root {
-                    final_tree_node_paths_set.insert(ancestor_path.to_path_buf());
-                    if ancestor_path == scan_root {
-                        break;
-                    }
-                    current_ancestor = ancestor_path.parent();
-                } else {
-                    break;
-                }
+fn read_file_body(file_path: &Path, cli_args: &cli::Cli) -> std::io::Result<FileBody> {
+    if !cli_args.include_binary_base64 && !cli_args.lossy {
+        return fs::read_to_string(file_path).map(FileBody::Text);
+    }
+    // Binary files must never go through UTF-8 decoding, so read raw bytes first.
+    let bytes = fs::read(file_path)?;
+    if utils::looks_binary(&bytes) {
+        if !cli_args.include_binary_base64 {
+            // --lossy is for text in another encoding; decoded binary would only be noise.
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "binary file, not decoded by --lossy",
+            ));
+        }
+        let size = bytes.len() as u64;
+        let encoded = (size <= cli_args.max_binary_bytes)
+            .then(|| base64::engine::general_purpose::STANDARD.encode(&bytes));
+        return Ok(FileBody::Binary { encoded, size });
+    }
+    match String::from_utf8(bytes) {
+        Ok(contents) => Ok(FileBody::Text(contents)),
+        Err(e) if cli_args.lossy => {
+            if !cli_args.no_warnings {
+                eprintln!(
+                    "⚠️ Warning: {} is not valid UTF-8; invalid bytes were replaced with �",
+                    file_path.display()
+                );
             }
+            Ok(FileBody::Text(
+                String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            ))
         }
+        Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
     }
-    // Ensure all actually yanked files and their ancestors are in the tree set.
-    for file_path in files_to_yank {
-        final_tree_node_paths_set.insert(file_path.clone());
-        let mut current_ancestor = file_path.parent();
-        while let Some(ancestor_path) = current_ancestor {
-            if ancestor_path.starts_with(scan_root) || ancestor_path == scan_root {
-                final_tree_node_paths_set.insert(ancestor_path.to_path_buf());
-                if ancestor_path == scan_root {
-                    break;
-                }
-                current_ancestor = ancestor_path.parent();
-            } else {
-                break;
+}
+
+// Why the content options left a file out.
+enum Skipped {
+    Directive,             // Marked repoyank:skip
+    OverTokenLimit(usize), // Above --max-file-tokens, with its token count
+}
+
+// Reads one file and applies the content options to it, from --redact through
+// --max-file-tokens. Returns the body, or why the file is left out, and the number of
+// redacted matches.
+fn prepare_file(
+    file_path: &Path,
+    redactor: &redact::Redactor,
+    cli_args: &cli::Cli,
+) -> (Result<std::io::Result<FileBody>, Skipped>, usize) {
+    let mut read_result = read_file_body(file_path, cli_args);
+    let mut redactions = 0;
+    if let Ok(FileBody::Text(contents)) = &mut read_result {
+        // Redaction comes first, so no later step (or --send) ever sees the secrets.
+        if !redactor.is_empty()
+            && let Some((redacted, replacements)) = redactor.redact(contents)
+        {
+            *contents = redacted;
+            redactions = replacements;
+        }
+        // Content rewrites run before --max-file-tokens, so files are measured as emitted.
+        // Directives come first, since they are themselves comments.
+        if !cli_args.ignore_directives {
+            let directives = FileDirectives::parse(file_path, contents, !cli_args.no_warnings);
+            if directives.skip {
+                return (Err(Skipped::Directive), redactions);
+            }
+            if let Some(truncated) = directives.truncate(contents) {
+                *contents = truncated;
             }
         }
+        if cli_args.strip_comments
+            && let Some(stripped) = comment_stripper::strip_comments(file_path, contents)
+        {
+            *contents = stripped;
+        }
+        if cli_args.collapse_blank_lines {
+            *contents = utils::collapse_blank_lines(contents);
+        }
+        if let Some(max_chars) = cli_args.truncate_lines.filter(|&max_chars| max_chars > 0)
+            && let Some(truncated) = utils::truncate_long_lines(contents, max_chars)
+        {
+            *contents = truncated;
+        }
+        if cli_args.head > 0
+            && let Some(head) = utils::head_lines(contents, cli_args.head)
+        {
+            *contents = head;
+        }
+    }
+    // Read errors are reported in the output instead.
+    if let (Some(max_tokens), Ok(body)) = (cli_args.max_file_tokens, &read_result) {
+        let tokens = utils::approx_tokens(body.emitted_text());
+        if tokens > max_tokens {
+            return (Err(Skipped::OverTokenLimit(tokens)), redactions);
+        }
     }
+    (Ok(read_result), redactions)
+}
 
-    let mut final_tree_nodes: Vec<(PathBuf, bool)> = final_tree_node_paths_set
-        .into_iter()
-        .map(|p| {
-            (
-                p.clone(),
-                all_paths_is_dir_map
-                    .get(&p)
-                    .copied()
-                    .unwrap_or_else(|| p.is_dir()),
-            )
-        })
-        .collect();
-    final_tree_nodes.sort_by(|(a, _), (b, _)| a.cmp(b));
-    final_tree_nodes.dedup_by(|(a, _), (b, _)| a == b);
+// What the listings of files (--toc, --tree-tokens, --summary-footer) show about a body.
+#[derive(Clone, Copy)]
+enum BodyStats {
+    Text { tokens: usize, lines: usize },
+    Binary { tokens: usize, size: u64 },
+    Unreadable,
+}
 
-    // Build the tree part of the output.
-    let output_tree_labels = tree_builder::build_tree_labels(&final_tree_nodes, scan_root);
-    let mut output_string_parts: Vec<String> = Vec::new();
+impl BodyStats {
+    fn of(read_result: &std::io::Result<FileBody>) -> Self {
+        match read_result {
+            Ok(body @ FileBody::Text(contents)) => BodyStats::Text {
+                tokens: utils::approx_tokens(body.emitted_text()),
+                lines: contents.trim_end().lines().count(),
+            },
+            Ok(body @ FileBody::Binary { size, .. }) => BodyStats::Binary {
+                tokens: utils::approx_tokens(body.emitted_text()),
+                size: *size,
+            },
+            Err(_) => BodyStats::Unreadable,
+        }
+    }
+}
 
-    let tree_string_for_clipboard: String = output_tree_labels.join("\n");
+// A file that made it through the content options. `body` is None when the output is
+// streamed: the file is then read once, as its block is written.
+struct YankedFile {
+    path: PathBuf,
+    body: Option<(BodyStats, std::io::Result<FileBody>)>,
+}
 
-    if !tree_string_for_clipboard.is_empty() || !files_to_yank.is_empty() {
-        output_string_parts.push(tree_string_for_clipboard);
-        output_string_parts.push("".to_string());
+impl YankedFile {
+    fn stats(&self) -> BodyStats {
+        let (stats, _) = self
+            .body
+            .as_ref()
+            .expect("the listings ahead of the file blocks read the files ahead");
+        *stats
     }
+}
 
-    // Append file contents.
-    for file_path in files_to_yank {
-        let relative_path = file_path.strip_prefix(scan_root).unwrap_or(file_path);
-        match fs::read_to_string(file_path) {
-            Ok(contents) => {
-                output_string_parts.push(format!("---\nFile: {}\n---", relative_path.display()));
-                output_string_parts.push("".to_string());
-                output_string_parts.push(contents.trim_end().to_string());
-                output_string_parts.push("".to_string());
+/// How many files are read ahead, in parallel, while the file blocks are written. Bounds the
+/// bodies a streamed run holds at once.
+const READ_AHEAD_FILES: usize = 64;
+
+// The files with their bodies, in order. Files that were not read ahead are read and
+// prepared here, a window at a time, and the redactions among them reported at the end.
+fn bodies_in_order<'a>(
+    files: Vec<YankedFile>,
+    redactor: &'a redact::Redactor,
+    cli_args: &'a cli::Cli,
+) -> impl Iterator<Item = (PathBuf, std::io::Result<FileBody>)> + 'a {
+    let mut pending = files.into_iter();
+    let mut window = Vec::new().into_iter();
+    let (mut redacted_files, mut total_replacements) = (0, 0);
+    std::iter::from_fn(move || {
+        while window.as_slice().is_empty() {
+            if pending.as_slice().is_empty() {
+                report_redactions(redacted_files, total_replacements, cli_args);
+                (redacted_files, total_replacements) = (0, 0);
+                return None;
             }
-            Err(e) => {
-                eprintln!(
-                    "⚠️ Warning: Could not read file {}: {}",
-                    file_path.display(),
-                    e
-                );
-                output_string_parts.push(format!(
-                    "---\nFile: {} (Error reading file: {})\n---",
-                    relative_path.display(),
-                    e
-                ));
-                output_string_parts.push("".to_string());
-                output_string_parts.push("[Content not available]".to_string());
-                output_string_parts.push("".to_string());
+            let next: Vec<YankedFile> = pending.by_ref().take(READ_AHEAD_FILES).collect();
+            let prepared: Vec<_> = next
+                .into_par_iter()
+                .map(|file| match file.body {
+                    Some((_, body)) => (file.path, Ok(body), 0),
+                    None => {
+                        let (prepared, redactions) = prepare_file(&file.path, redactor, cli_args);
+                        (file.path, prepared, redactions)
+                    }
+                })
+                .collect();
+            let mut bodies = Vec::with_capacity(prepared.len());
+            for (path, prepared, redactions) in prepared {
+                if redactions > 0 {
+                    redacted_files += 1;
+                    total_replacements += redactions;
+                }
+                // Only a file marked repoyank:skip after its first lines were checked ends
+                // up here; it is already in the tree, but its block is left out.
+                if let Ok(body) = prepared {
+                    bodies.push((path, body));
+                }
             }
+            window = bodies.into_iter();
         }
+        window.next()
+    })
+}
+
+fn report_redactions(redacted_files: usize, total_replacements: usize, cli_args: &cli::Cli) {
+    if total_replacements > 0 && !cli_args.quiet {
+        eprintln!(
+            "🔒 Redacted {} match(es) in {} file(s).",
+            total_replacements, redacted_files
+        );
     }
+}
+
+fn items_by_path(items: &[tui::SelectableItem]) -> HashMap<&Path, &tui::SelectableItem> {
+    items
+        .iter()
+        .map(|item| (item.path.as_path(), item))
+        .collect()
+}
 
-    let mut final_output_string = output_string_parts.join("\n");
-    if !final_output_string.is_empty() {
-        // Ensure single trailing newline.
-        final_output_string = final_output_string.trim_end_matches('\n').to_string();
-        final_output_string.push('\n');
+// A file's entry for `sort_tree_entries`, with the size and time recorded on its item.
+fn file_entry(
+    path: &Path,
+    items_by_path: &HashMap<&Path, &tui::SelectableItem>,
+) -> file_scanner::ScannedEntry {
+    let item = items_by_path.get(path);
+    file_scanner::ScannedEntry {
+        path: path.to_path_buf(),
+        is_dir: false,
+        size: item.map_or(0, |item| item.size),
+        modified: item.and_then(|item| item.modified),
+        is_symlink: false,
     }
+}
 
-    // Handle empty output case.
-    if final_output_string.trim().is_empty() && files_to_yank.is_empty() {
-        if scan_root.exists()
-            && scan_root.is_dir()
-            && final_tree_nodes.iter().any(|(p, _)| p == scan_root)
-        {
-            final_output_string = format!("./\n\n(No files selected or matched criteria)\n");
+// Picks the files beyond the first `max_per_dir` of each directory, in --sort order. Sizes
+// and times for the sort come from the items.
+fn files_over_per_directory_cap(
+    files_to_yank: &[PathBuf],
+    items: &[tui::SelectableItem],
+    max_per_dir: usize,
+    sort_order: cli::SortOrder,
+) -> Vec<PathBuf> {
+    let items_by_path = items_by_path(items);
+    let mut entries: Vec<file_scanner::ScannedEntry> = files_to_yank
+        .iter()
+        .map(|path| file_entry(path, &items_by_path))
+        .collect();
+    // Only files are sorted here, so --dirs-first makes no difference.
+    tree_builder::sort_tree_entries(&mut entries, sort_order, false);
+    let mut kept_per_dir: HashMap<&Path, usize> = HashMap::new();
+    let mut over_cap = Vec::new();
+    for file_scanner::ScannedEntry { path, .. } in &entries {
+        let kept = kept_per_dir
+            .entry(path.parent().unwrap_or_else(|| Path::new("")))
+            .or_insert(0);
+        if *kept < max_per_dir {
+            *kept += 1;
         } else {
-            final_output_string = format!("(No files selected or matched criteria)\n");
+            over_cap.push(path.clone());
         }
     }
-    Ok((output_tree_labels, final_output_string))
+    over_cap
 }
 
-// Performs the final action: printing for dry-run or copying to clipboard.
-fn perform_final_action(
-    output_string: &str,
-    files_to_yank_count: usize,
-    is_dry_run: bool,
-    initial_scan_was_empty_and_not_default: bool,
-    output_tree_labels_for_console: &[String],
-    output_file: &Option<std::path::PathBuf>,
-) -> Result<()> {
-    if is_dry_run {
-        print!("{}", output_string);
-        if files_to_yank_count == 0 {
-            if !output_string.contains("(No files selected or matched criteria)")
-                && !initial_scan_was_empty_and_not_default
-            {
-                println!("(Dry run: No files would have been copied based on selection/criteria)");
+// Takes files out of the selection entirely, so they drop out of the tree as well.
+fn remove_from_selection(
+    files_to_yank: &mut Vec<PathBuf>,
+    final_tui_items_for_tree: &mut [tui::SelectableItem],
+    removed: &HashSet<&PathBuf>,
+) {
+    files_to_yank.retain(|path| !removed.contains(path));
+    for item in final_tui_items_for_tree {
+        if removed.contains(&item.path) {
+            item.state = tui::SelectionState::NotSelected;
+        }
+    }
+}
+
+// Prints a table of files, lines, bytes and tokens per directory plus a total (--count-only).
+// Counts are of the contents as they would be emitted, after every content rewrite.
+fn print_count_summary(
+    file_contents: impl Iterator<Item = (PathBuf, std::io::Result<FileBody>)>,
+    scan_root: &Path,
+    no_warnings: bool,
+) {
+    // (files, lines, bytes, tokens) per directory, keyed by the path below the scan root.
+    let mut per_dir: BTreeMap<PathBuf, (usize, usize, u64, usize)> = BTreeMap::new();
+    for (file_path, read_result) in file_contents {
+        let body = match read_result {
+            Ok(body) => body,
+            Err(e) => {
+                if !no_warnings {
+                    eprintln!(
+                        "⚠️ Warning: Could not read file {}: {}",
+                        file_path.display(),
+                        e
+                    );
+                }
+                continue;
             }
+        };
+        let text = body.emitted_text();
+        let dir = file_path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(scan_root).ok())
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        let row = per_dir.entry(dir).or_default();
+        row.0 += 1;
+        row.1 += text.lines().count();
+        row.2 += text.len() as u64;
+        row.3 += utils::approx_tokens(text);
+    }
+
+    let total = per_dir.values().fold((0, 0, 0, 0), |acc, row| {
+        (acc.0 + row.0, acc.1 + row.1, acc.2 + row.2, acc.3 + row.3)
+    });
+    let format_row = |label: String, (files, lines, bytes, tokens): (usize, usize, u64, usize)| {
+        [
+            label,
+            files.to_string(),
+            lines.to_string(),
+            utils::format_size(bytes),
+            utils::format_token_count(tokens),
+        ]
+    };
+    let mut rows = vec![[
+        "Directory".to_string(),
+        "Files".to_string(),
+        "Lines".to_string(),
+        "Bytes".to_string(),
+        "≈ Tokens".to_string(),
+    ]];
+    for (dir, row) in &per_dir {
+        let label = if dir.as_os_str().is_empty() {
+            "./".to_string()
         } else {
-            let tokens = utils::approx_tokens(output_string);
+            format!("{}/", dir.display())
+        };
+        rows.push(format_row(label, *row));
+    }
+    rows.push(format_row("Total".to_string(), total));
+
+    let widths: Vec<usize> = (0..5)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for (idx, row) in rows.iter().enumerate() {
+        if idx == rows.len() - 1 {
             println!(
-                "(Dry run: Would copy {} files (≈ {} tokens). Clipboard not affected.)",
-                files_to_yank_count, tokens
+                "{}",
+                "-".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1))
             );
         }
-    } else if files_to_yank_count == 0 {
-        // This path should only be hit if something went wrong or an edge case led to no files
-        // after initial checks passed.
-        if !output_string.contains("(No files selected or matched criteria)") {
-            println!("{}", output_string.trim_end());
-        }
-        println!("No files were ultimately selected to copy. Exiting.");
-        std::process::exit(1); // Non-zero exit for actual copy operation with no files.
-    } else if files_to_yank_count > 0 {
-        // Print the tree structure to console
-        if !output_tree_labels_for_console.is_empty() {
-            for label in output_tree_labels_for_console {
-                println!("{}", label);
-            }
-            println!();
+        // The directory column is left-aligned, the numbers right-aligned.
+        let mut line = format!("{:<width$}", row[0], width = widths[0]);
+        for (cell, width) in row.iter().zip(&widths).skip(1) {
+            line.push_str(&format!("  {:>width$}", cell, width = width));
         }
+        println!("{}", line);
+    }
+}
 
-        let tokens = utils::approx_tokens(output_string);
-        if let Some(output_path) = output_file.as_ref() {
-            if let Some(parent) = output_path.parent() {
-                if !parent.as_os_str().is_empty() {
-                    fs::create_dir_all(parent)?;
+// Prints the --manifest: the yanked paths relative to `scan_root`, in sorted order.
+fn print_manifest(
+    files_to_yank: &[PathBuf],
+    scan_root: &Path,
+    format: cli::ManifestFormat,
+) -> Result<()> {
+    let mut paths: Vec<String> = files_to_yank
+        .iter()
+        .map(|path| {
+            let relative_path = path.strip_prefix(scan_root).unwrap_or(path);
+            relative_path.display().to_string()
+        })
+        .collect();
+    paths.sort();
+    match format {
+        cli::ManifestFormat::Lines => {
+            let mut stdout = std::io::stdout().lock();
+            for path in &paths {
+                exit_on_broken_pipe(writeln!(stdout, "{}", path))?;
+            }
+        }
+        cli::ManifestFormat::Json => println!("{}", serde_json::to_string_pretty(&paths)?),
+    }
+    Ok(())
+}
+
+// Path shown for a file in the output: relative to the scan root, or with --paths-from-cwd
+// relative to the current directory when the file lies under it.
+fn display_path<'a>(path: &'a Path, scan_root: &Path, cwd: Option<&Path>) -> &'a Path {
+    if let Some(cwd) = cwd
+        && let Ok(relative_to_cwd) = path.strip_prefix(cwd)
+    {
+        return relative_to_cwd;
+    }
+    path.strip_prefix(scan_root).unwrap_or(path)
+}
+
+// Path in a file's header (and its --toc entry), in the chosen --path-style.
+fn header_path<'a>(
+    path: &'a Path,
+    scan_root: &Path,
+    cwd: Option<&Path>,
+    style: cli::PathStyle,
+) -> Cow<'a, Path> {
+    match style {
+        cli::PathStyle::Relative => Cow::Borrowed(display_path(path, scan_root, cwd)),
+        cli::PathStyle::Absolute => std::path::absolute(path)
+            .map(Cow::Owned)
+            .unwrap_or(Cow::Borrowed(path)),
+        cli::PathStyle::Name => Cow::Borrowed(path.file_name().map_or(path, Path::new)),
+    }
+}
+
+// Root label for --prefix-tree-root: the given label, or the scan root's directory name
+// (e.g. `myproject/`) when none was given.
+fn custom_root_label(label: &str, scan_root: &Path) -> String {
+    if !label.is_empty() {
+        return label.to_string();
+    }
+    scan_root
+        .canonicalize()
+        .ok()
+        .and_then(|root| {
+            root.file_name()
+                .map(|name| format!("{}/", name.to_string_lossy()))
+        })
+        .unwrap_or_else(|| "./".to_string())
+}
+
+// Label for the tree root as seen from `cwd`: `../../` when the cwd is inside the root,
+// `sub/dir/` when the root is inside the cwd, and None when neither applies.
+fn root_label_from_cwd(scan_root: &Path, cwd: &Path) -> Option<String> {
+    if let Ok(cwd_below_root) = cwd.strip_prefix(scan_root) {
+        let depth = cwd_below_root.components().count();
+        return Some(if depth == 0 {
+            "./".to_string()
+        } else {
+            "../".repeat(depth)
+        });
+    }
+    let root_below_cwd = scan_root.strip_prefix(cwd).ok()?;
+    Some(format!("{}/", root_below_cwd.display()))
+}
+
+const NO_FILES_PLACEHOLDER: &str = "(No files selected or matched criteria)";
+
+/// Where the output goes: streamed to a file or stdout as it is assembled, or buffered for
+/// the clipboard and --send, which need the whole text.
+enum OutputTarget {
+    Stream(Box<dyn Write>),
+    Buffer(String),
+}
+
+/// Receives the output block by block. Blocks are joined with newlines and the output ends
+/// in exactly one newline, so streaming gives the same bytes as buffering.
+struct OutputSink {
+    target: OutputTarget,
+    started: bool,
+    pending_newlines: usize, // Held back so trailing newlines can be collapsed at the end
+    chars_written: usize,
+    no_files_placeholder: bool,
+    file_blocks: Vec<(usize, PathBuf)>, // Buffer offset where each file block starts, for --split
+    trailer_start: Option<usize>,       // Buffer offset of the sections after the file blocks
+}
+
+// Stdout closed by its reader (e.g. `| head`) ends the run quietly, like other CLI tools.
+fn exit_on_broken_pipe(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => std::process::exit(0),
+        result => result,
+    }
+}
+
+/// The finished output. `text` is only kept when it was buffered.
+struct RenderedOutput {
+    text: Option<String>,
+    tokens: usize,
+    no_files_placeholder: bool,
+    file_blocks: Vec<(usize, PathBuf)>,
+    trailer_start: Option<usize>,
+}
+
+impl OutputSink {
+    fn new(target: OutputTarget) -> Self {
+        OutputSink {
+            target,
+            started: false,
+            pending_newlines: 0,
+            chars_written: 0,
+            no_files_placeholder: false,
+            file_blocks: Vec::new(),
+            trailer_start: None,
+        }
+    }
+
+    /// Notes that the block of `path` starts here, so --split can cut the buffered output
+    /// between files. The separating newlines are still pending, so they go with the block.
+    fn mark_file_block(&mut self, path: &Path) {
+        if let OutputTarget::Buffer(buffer) = &self.target {
+            self.file_blocks.push((buffer.len(), path.to_path_buf()));
+        }
+    }
+
+    /// Notes that the file blocks end here; what follows goes with the last --split chunk.
+    fn mark_trailer(&mut self) {
+        if let OutputTarget::Buffer(buffer) = &self.target {
+            self.trailer_start = Some(buffer.len());
+        }
+    }
+
+    fn push(&mut self, block: &str) -> std::io::Result<()> {
+        if self.started {
+            self.pending_newlines += 1;
+        }
+        self.started = true;
+        let text = block.trim_end_matches('\n');
+        if !text.is_empty() {
+            self.write_raw(&"\n".repeat(self.pending_newlines))?;
+            self.write_raw(text)?;
+            self.pending_newlines = 0;
+        }
+        self.pending_newlines += block.len() - text.len();
+        Ok(())
+    }
+
+    fn write_raw(&mut self, text: &str) -> std::io::Result<()> {
+        match &mut self.target {
+            OutputTarget::Stream(writer) => exit_on_broken_pipe(writer.write_all(text.as_bytes()))?,
+            OutputTarget::Buffer(buffer) => buffer.push_str(text),
+        }
+        self.chars_written += text.chars().count();
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.chars_written == 0
+    }
+
+    /// Writes the final newline and flushes a streamed target.
+    fn finish(mut self) -> std::io::Result<RenderedOutput> {
+        if !self.is_empty() {
+            self.write_raw("\n")?;
+        }
+        let text = match self.target {
+            OutputTarget::Stream(mut writer) => {
+                exit_on_broken_pipe(writer.flush())?;
+                None
+            }
+            OutputTarget::Buffer(buffer) => Some(buffer),
+        };
+        Ok(RenderedOutput {
+            text,
+            tokens: utils::approx_tokens_for_chars(self.chars_written),
+            no_files_placeholder: self.no_files_placeholder,
+            file_blocks: self.file_blocks,
+            trailer_start: self.trailer_start,
+        })
+    }
+}
+
+// Writes the output, the directory tree followed by the file contents, to `sink`.
+// Returns the tree labels for the console.
+#[allow(clippy::too_many_arguments)]
+fn write_output(
+    final_tui_items_for_tree: &[tui::SelectableItem],
+    files_to_yank: &[PathBuf],
+    mut file_contents: Vec<YankedFile>,
+    scan_root: &Path,
+    omitted_files: &[PathBuf],
+    template: Option<&template::Template>,
+    redactor: &redact::Redactor,
+    cli_args: &cli::Cli,
+    sink: &mut OutputSink,
+) -> Result<Vec<String>> {
+    // Determine nodes for the output tree display, each with whether it is a directory. The
+    // items know their kind; ancestors are directories and yanked files are files.
+    let mut final_tree_node_kinds: HashMap<PathBuf, bool> = HashMap::new();
+    if scan_root.exists() && scan_root.is_dir() {
+        final_tree_node_kinds.insert(scan_root.to_path_buf(), true);
+    }
+
+    // Add selected/partially selected items and their ancestors from TUI/headless structured items.
+    for item in final_tui_items_for_tree {
+        if item.state == tui::SelectionState::FullySelected
+            || item.state == tui::SelectionState::PartiallySelected
+        {
+            final_tree_node_kinds.insert(item.path.clone(), item.is_dir);
+            let mut current_ancestor = item.path.parent();
+            while let Some(ancestor_path) = current_ancestor {
+                if ancestor_path.starts_with(scan_root) || ancestor_path == scan_root {
+                    final_tree_node_kinds.insert(ancestor_path.to_path_buf(), true);
+                    if ancestor_path == scan_root {
+                        break;
+                    }
+                    current_ancestor = ancestor_path.parent();
+                } else {
+                    break;
                 }
             }
-            fs::write(output_path, output_string)?;
+        }
+    }
+    // Ensure all actually yanked files and their ancestors are in the tree set.
+    for file_path in files_to_yank {
+        final_tree_node_kinds.insert(file_path.clone(), false);
+        let mut current_ancestor = file_path.parent();
+        while let Some(ancestor_path) = current_ancestor {
+            if ancestor_path.starts_with(scan_root) || ancestor_path == scan_root {
+                final_tree_node_kinds.insert(ancestor_path.to_path_buf(), true);
+                if ancestor_path == scan_root {
+                    break;
+                }
+                current_ancestor = ancestor_path.parent();
+            } else {
+                break;
+            }
+        }
+    }
+
+    let mut final_tree_nodes: Vec<(PathBuf, bool)> = final_tree_node_kinds.into_iter().collect();
+    // Prune directories without a file anywhere below them (e.g. a selected empty directory,
+    // or one whose files were all skipped), unless --include-empty-dirs. Marking the
+    // ancestors of every file keeps exactly the directories with a surviving descendant.
+    if !cli_args.include_empty_dirs {
+        let mut dirs_with_files: HashSet<&Path> = HashSet::new();
+        for (path, _) in final_tree_nodes.iter().filter(|(_, is_dir)| !*is_dir) {
+            for ancestor in path.ancestors().skip(1) {
+                if !dirs_with_files.insert(ancestor) {
+                    break; // Already marked, and so are all further ancestors.
+                }
+            }
+        }
+        let dirs_with_files: HashSet<PathBuf> =
+            dirs_with_files.into_iter().map(Path::to_path_buf).collect();
+        final_tree_nodes.retain(|(path, is_dir)| {
+            !*is_dir || path == scan_root || dirs_with_files.contains(path)
+        });
+    }
+    let items_by_path = items_by_path(final_tui_items_for_tree);
+    let mut final_tree_entries: Vec<file_scanner::ScannedEntry> = final_tree_nodes
+        .into_iter()
+        .map(|(path, is_dir)| {
+            if is_dir {
+                file_scanner::ScannedEntry::directory(path)
+            } else {
+                file_entry(&path, &items_by_path)
+            }
+        })
+        .collect();
+    tree_builder::sort_tree_entries(&mut final_tree_entries, cli_args.sort, cli_args.dirs_first);
+    let mut final_tree_nodes: Vec<(PathBuf, bool)> = final_tree_entries
+        .into_iter()
+        .map(file_scanner::ScannedEntry::into_path_and_kind)
+        .collect();
+    final_tree_nodes.dedup_by(|(a, _), (b, _)| a == b);
+
+    // Any sort other than by name only makes sense if the file blocks follow the tree.
+    if cli_args.output_order == cli::OutputOrder::Tree
+        || cli_args.sort != cli::SortOrder::Name
+        || cli_args.dirs_first
+    {
+        // The tree is rendered from `final_tree_nodes` in order, so a file's position
+        // there is exactly its line in the tree.
+        let tree_position: HashMap<&Path, usize> = final_tree_nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, (path, _))| (path.as_path(), idx))
+            .collect();
+        file_contents.sort_by_key(|file| tree_position.get(file.path.as_path()).copied());
+    }
+    // --pin moves matching files to the front, in the order of the pin globs. It runs before
+    // grouping so that with --group-by-ext a pinned file leads its group, and its group leads.
+    if !cli_args.pin_globs.is_empty() {
+        let pin_patterns = glob_patterns(&cli_args.pin_globs, "--pin")?;
+        file_contents.sort_by_key(|file| {
+            let relative_path = file.path.strip_prefix(scan_root).unwrap_or(&file.path);
+            pin_patterns
+                .iter()
+                .position(|p| p.matches_path(relative_path))
+                .unwrap_or(usize::MAX)
+        });
+    }
+    if cli_args.group_by_ext {
+        // Groups follow the order each extension first appears; the stable sort keeps path
+        // order within a group. Only the file blocks move, the tree stays structural.
+        let mut group_order: Vec<String> = Vec::new();
+        for file in &file_contents {
+            let ext = utils::extension_key(&file.path);
+            if !group_order.contains(&ext) {
+                group_order.push(ext);
+            }
+        }
+        file_contents.sort_by_key(|file| {
+            let ext = utils::extension_key(&file.path);
+            group_order.iter().position(|group| *group == ext)
+        });
+    }
+
+    // Build the tree part of the output (skipped entirely with --no-tree).
+    // --tree-output still needs the tree even when it is left out of the output itself.
+    // The canonical cwd for --paths-from-cwd. A relative scan root is already
+    // relative to the cwd, so only absolute roots need the translation.
+    let cwd = if cli_args.paths_from_cwd && scan_root.is_absolute() {
+        std::env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .ok()
+    } else {
+        None
+    };
+    let mut output_tree_labels = if cli_args.no_tree && cli_args.tree_output.is_none() {
+        Vec::new()
+    } else {
+        tree_builder::build_tree_labels(&final_tree_nodes, scan_root, cli_args.icons)
+    };
+    // Only the root line carries a path; the other labels are bare names.
+    if let Some(cwd) = cwd.as_deref()
+        && final_tree_nodes.first().map(|(path, _)| path.as_path()) == Some(scan_root)
+        && let Some(root_label) = root_label_from_cwd(scan_root, cwd)
+        && let Some(first_label) = output_tree_labels.first_mut()
+    {
+        *first_label = root_label;
+    }
+    if let Some(label) = cli_args.prefix_tree_root.as_deref()
+        && final_tree_nodes.first().map(|(path, _)| path.as_path()) == Some(scan_root)
+        && let Some(first_label) = output_tree_labels.first_mut()
+    {
+        *first_label = custom_root_label(label, scan_root);
+    }
+    // With --compact-tree the labels no longer line up with `final_tree_nodes`.
+    let compacted_tree_nodes;
+    let mut tree_label_paths: &[(PathBuf, bool)] = &final_tree_nodes;
+    if cli_args.compact_tree && !output_tree_labels.is_empty() {
+        let (labels, paths) =
+            tree_builder::compact_tree_labels(output_tree_labels, &final_tree_nodes, scan_root);
+        output_tree_labels = labels;
+        compacted_tree_nodes = paths;
+        tree_label_paths = &compacted_tree_nodes;
+    }
+    if cli_args.tree_tokens && !output_tree_labels.is_empty() {
+        let token_counts: HashMap<PathBuf, usize> = file_contents
+            .iter()
+            .filter_map(|file| match file.stats() {
+                BodyStats::Text { tokens, .. } | BodyStats::Binary { tokens, .. } => {
+                    Some((file.path.clone(), tokens))
+                }
+                BodyStats::Unreadable => None,
+            })
+            .collect();
+        tree_builder::annotate_tree_labels_with_tokens(
+            &mut output_tree_labels,
+            tree_label_paths,
+            &token_counts,
+        );
+    }
+
+    // --template takes over the layout: its header and footer around one rendering of the
+    // file section per file, instead of the built-in tree, headers and extra sections (the
+    // options for those conflict with it). The rendering is pushed as one block: --split
+    // conflicts with --template too, so the file blocks need no marks.
+    if let Some(template) = template {
+        let tree = if cli_args.no_tree {
+            String::new()
+        } else {
+            output_tree_labels.join("\n")
+        };
+        let mut rendered = template.render_header(&tree);
+        for (index, (file_path, read_result)) in
+            bodies_in_order(file_contents, redactor, cli_args).enumerate()
+        {
+            let content = match &read_result {
+                Ok(FileBody::Text(contents)) => contents.trim_end(),
+                Ok(FileBody::Binary {
+                    encoded: Some(encoded),
+                    ..
+                }) => encoded.as_str(),
+                Ok(FileBody::Binary { encoded: None, .. }) => "[Binary content omitted]",
+                Err(e) => {
+                    if !cli_args.no_warnings {
+                        eprintln!(
+                            "⚠️ Warning: Could not read file {}: {}",
+                            file_path.display(),
+                            e
+                        );
+                    }
+                    "[Content not available]"
+                }
+            };
+            rendered.push_str(
+                &template.render_file(&template::FileValues {
+                    path: &header_path(&file_path, scan_root, cwd.as_deref(), cli_args.path_style)
+                        .display()
+                        .to_string(),
+                    content,
+                    lang: &utils::extension_key(&file_path),
+                    index: index + 1,
+                }),
+            );
+        }
+        rendered.push_str(&template.render_footer(&tree));
+        sink.push(&rendered)?;
+        if sink.is_empty() && files_to_yank.is_empty() {
+            sink.push(NO_FILES_PLACEHOLDER)?;
+            sink.no_files_placeholder = true;
+        }
+        return Ok(output_tree_labels);
+    }
+
+    // A numbered index of the file blocks, in the order they are emitted (--toc).
+    if cli_args.toc && !file_contents.is_empty() {
+        sink.push("---\nTable of contents\n---")?;
+        sink.push("")?;
+        for (number, file) in file_contents.iter().enumerate() {
+            sink.push(&format!(
+                "{}. {} ({})",
+                number + 1,
+                header_path(&file.path, scan_root, cwd.as_deref(), cli_args.path_style).display(),
+                file_stats(file.stats())
+            ))?;
+        }
+        sink.push("")?;
+    }
+
+    let tree_string_for_clipboard: String = output_tree_labels.join("\n");
+
+    if !cli_args.no_tree && !tree_string_for_clipboard.is_empty() {
+        sink.push(&tree_string_for_clipboard)?;
+        sink.push("")?;
+    }
+
+    // --annotate-git: one `git log` per file; silently skipped outside a repository.
+    let mut last_commits: HashMap<PathBuf, String> = HashMap::new();
+    if cli_args.annotate_git && git::is_inside_work_tree(scan_root) {
+        for file in &file_contents {
+            if let Some(summary) = git::last_commit_summary(&file.path) {
+                last_commits.insert(file.path.clone(), summary);
+            }
+        }
+    }
+
+    // Append file contents, reading the files that were not read ahead as they are written.
+    // The --summary-footer is collected along the way, since it follows the file blocks.
+    let mut current_ext_group: Option<String> = None;
+    let mut summary_footer: Vec<String> = Vec::new();
+    let (mut total_lines, mut total_tokens) = (0, 0);
+    for (file_path, read_result) in bodies_in_order(file_contents, redactor, cli_args) {
+        sink.mark_file_block(&file_path);
+        if cli_args.group_by_ext {
+            let ext = utils::extension_key(&file_path);
+            if current_ext_group.as_ref() != Some(&ext) {
+                let heading = match utils::language_for_extension(&ext) {
+                    Some(language) => format!("{} files", language),
+                    None if ext.is_empty() => "Files without extension".to_string(),
+                    None => format!(".{} files", ext),
+                };
+                sink.push(&format!("=== {} ===", heading))?;
+                sink.push("")?;
+                current_ext_group = Some(ext);
+            }
+        }
+        let relative_path = header_path(&file_path, scan_root, cwd.as_deref(), cli_args.path_style);
+        // Extra header lines below `File:`, from --label-language and --annotate-git.
+        let mut extra_header_lines = String::new();
+        if cli_args.label_language {
+            let language = utils::language_for_extension(&utils::extension_key(&file_path))
+                .unwrap_or("Unknown");
+            extra_header_lines.push_str(&format!("\nLanguage: {}", language));
+        }
+        if let Some(summary) = last_commits.get(&file_path) {
+            extra_header_lines.push_str(&format!("\nLast modified: {}", summary));
+        }
+        if cli_args.summary_footer {
+            // Binary files add their (base64) tokens but no lines; unreadable ones add neither.
+            let stats = BodyStats::of(&read_result);
+            match stats {
+                BodyStats::Text { tokens, lines } => {
+                    total_tokens += tokens;
+                    total_lines += lines;
+                }
+                BodyStats::Binary { tokens, .. } => total_tokens += tokens,
+                BodyStats::Unreadable => {}
+            }
+            summary_footer.push(format!(
+                "{} ({})",
+                relative_path.display(),
+                file_stats(stats)
+            ));
+        }
+        match read_result {
+            Ok(FileBody::Text(contents)) => {
+                sink.push(&format!(
+                    "---\nFile: {}{}\n---",
+                    relative_path.display(),
+                    extra_header_lines
+                ))?;
+                sink.push("")?;
+                sink.push(contents.trim_end())?;
+                sink.push("")?;
+            }
+            Ok(FileBody::Binary {
+                encoded: Some(encoded),
+                size,
+            }) => {
+                sink.push(&format!(
+                    "---\nFile: {} (binary, base64, {} bytes){}\n---",
+                    relative_path.display(),
+                    size,
+                    extra_header_lines
+                ))?;
+                sink.push("")?;
+                sink.push(&encoded)?;
+                sink.push("")?;
+            }
+            Ok(FileBody::Binary {
+                encoded: None,
+                size,
+            }) => {
+                sink.push(&format!(
+                    "---\nFile: {} (binary, {} bytes, over --max-binary-bytes){}\n---",
+                    relative_path.display(),
+                    size,
+                    extra_header_lines
+                ))?;
+                sink.push("")?;
+                sink.push("[Binary content omitted]")?;
+                sink.push("")?;
+            }
+            Err(e) => {
+                if !cli_args.no_warnings {
+                    eprintln!(
+                        "⚠️ Warning: Could not read file {}: {}",
+                        file_path.display(),
+                        e
+                    );
+                }
+                sink.push(&format!(
+                    "---\nFile: {} (Error reading file: {})\n---",
+                    relative_path.display(),
+                    e
+                ))?;
+                sink.push("")?;
+                sink.push("[Content not available]")?;
+                sink.push("")?;
+            }
+        }
+    }
+
+    sink.mark_trailer();
+
+    // Append the manifest of matched-but-omitted files (--list-omitted).
+    if !omitted_files.is_empty() {
+        sink.push("---\nOmitted files (matched but not included)\n---")?;
+        sink.push("")?;
+        for omitted_path in omitted_files {
+            let relative_path = display_path(omitted_path, scan_root, cwd.as_deref());
+            sink.push(&relative_path.display().to_string())?;
+        }
+        sink.push("")?;
+    }
+
+    if !summary_footer.is_empty() {
+        sink.push("---\nSummary\n---")?;
+        sink.push("")?;
+        for line in &summary_footer {
+            sink.push(line)?;
+        }
+        sink.push("")?;
+        sink.push(&format!(
+            "Total: {} files, ≈ {} tokens, {} lines (file contents only; the token count reported for the whole output includes this summary)",
+            summary_footer.len(),
+            utils::format_token_count(total_tokens),
+            total_lines
+        ))?;
+        sink.push("")?;
+    }
+
+    // Handle empty output case.
+    if sink.is_empty() && files_to_yank.is_empty() {
+        if !cli_args.no_tree
+            && scan_root.exists()
+            && scan_root.is_dir()
+            && final_tree_nodes.iter().any(|(p, _)| p == scan_root)
+        {
+            sink.push("./")?;
+            sink.push("")?;
+        }
+        sink.push(NO_FILES_PLACEHOLDER)?;
+        sink.no_files_placeholder = true;
+    }
+    Ok(output_tree_labels)
+}
+
+// Token and line counts of one file for the --toc and --summary-footer listings.
+fn file_stats(stats: BodyStats) -> String {
+    match stats {
+        BodyStats::Text { tokens, lines } => format!(
+            "≈ {} tokens, {} lines",
+            utils::format_token_count(tokens),
+            lines
+        ),
+        BodyStats::Binary { size, .. } => format!("binary, {} bytes", size),
+        BodyStats::Unreadable => "unreadable".to_string(),
+    }
+}
+
+// Creates (or truncates) the file at `path`, creating any missing parent directories first.
+fn create_file_creating_parents(path: &Path) -> Result<fs::File> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::File::create(path)?)
+}
+
+// Writes `contents` to `path`, creating any missing parent directories first.
+fn write_file_creating_parents(path: &Path, contents: &str) -> Result<()> {
+    create_file_creating_parents(path)?.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+// Whether the output goes to stdout (dry run) or --output as it is assembled. The clipboard
+// and --send need the whole text, --split cuts the finished text into chunks, and
+// --max-tokens-strict must measure it before any of it is delivered.
+fn streams_output(cli_args: &cli::Cli) -> bool {
+    let strict_budget = cli_args.max_tokens.is_some() && cli_args.max_tokens_strict;
+    cli_args.split.is_none()
+        && !strict_budget
+        && (cli_args.dry_run || (cli_args.output_file.is_some() && !sends_output(cli_args)))
+}
+
+// Whether the files are read before the output starts, rather than each once as its block is
+// written. Buffered output is assembled at the end anyway, and --toc, --tree-tokens and
+// --max-file-tokens need every file's stats before the tree is written.
+fn reads_ahead(cli_args: &cli::Cli) -> bool {
+    let streams = streams_output(cli_args) || cli_args.count_only;
+    !streams || cli_args.toc || cli_args.tree_tokens || cli_args.max_file_tokens.is_some()
+}
+
+// Warns when the output is over --max-tokens. With --max-tokens-strict that ends the run
+// instead, before the (buffered) output is delivered.
+fn check_token_budget(output: &RenderedOutput, cli_args: &cli::Cli) -> Result<()> {
+    let tokens = output.tokens;
+    if let Some(max_tokens) = cli_args.max_tokens
+        && tokens > max_tokens
+    {
+        eprintln!(
+            "⚠️ Warning: The output is ≈ {} tokens, {} over --max-tokens {}. It may not fit the model's context.",
+            tokens,
+            tokens - max_tokens,
+            max_tokens
+        );
+        if cli_args.max_tokens_strict {
+            let action = if cli_args.dry_run {
+                "printing"
+            } else if cli_args.output_file.is_some() {
+                "writing"
+            } else {
+                "copying"
+            };
+            eprintln!("Not {} because of --max-tokens-strict.", action);
+            return Err(Exit::OverTokenBudget.into());
+        }
+    }
+    Ok(())
+}
+
+fn output_target(cli_args: &cli::Cli, files_to_yank_count: usize) -> Result<OutputTarget> {
+    if !streams_output(cli_args) {
+        return Ok(OutputTarget::Buffer(String::new()));
+    }
+    if cli_args.dry_run {
+        return Ok(OutputTarget::Stream(Box::new(std::io::stdout())));
+    }
+    // With nothing to yank no file is written, only the nothing-selected message.
+    if let Some(output_path) = cli_args.output_file.as_ref()
+        && files_to_yank_count > 0
+    {
+        let file = create_file_creating_parents(output_path)?;
+        return Ok(OutputTarget::Stream(Box::new(BufWriter::new(file))));
+    }
+    Ok(OutputTarget::Buffer(String::new()))
+}
+
+/// One --split chunk: the shared preamble (tree, TOC) followed by a run of whole file blocks.
+struct Chunk {
+    text: String,
+    files: usize,
+    tokens: usize,
+}
+
+// Cuts the buffered output into chunks of whole file blocks that stay within `budget`
+// tokens, each starting with the preamble before the first file block. The sections after
+// the file blocks go with the last chunk. A file too big for a chunk of its own still gets
+// one, with a warning.
+fn split_into_chunks(
+    text: &str,
+    output: &RenderedOutput,
+    budget: usize,
+    no_warnings: bool,
+) -> Vec<Chunk> {
+    let tokens_of = |part: &str| utils::approx_tokens_for_chars(part.chars().count());
+    let Some(&(first_block_start, _)) = output.file_blocks.first() else {
+        return vec![Chunk {
+            text: text.to_string(),
+            files: 0,
+            tokens: output.tokens,
+        }];
+    };
+    let preamble = &text[..first_block_start];
+    let preamble_tokens = tokens_of(preamble);
+    let trailer_start = output.trailer_start.unwrap_or(text.len());
+
+    let mut chunks = Vec::new();
+    let mut blocks = String::new();
+    let (mut block_count, mut blocks_tokens) = (0, 0);
+    for (idx, (start, path)) in output.file_blocks.iter().enumerate() {
+        let end = output
+            .file_blocks
+            .get(idx + 1)
+            .map_or(trailer_start, |(next_start, _)| *next_start);
+        let block = &text[*start..end];
+        let block_tokens = tokens_of(block);
+        if preamble_tokens + block_tokens > budget && !no_warnings {
+            eprintln!(
+                "⚠️ Warning: {} is ≈ {} tokens with the tree, over the --split budget of {}; it gets a chunk of its own.",
+                path.display(),
+                preamble_tokens + block_tokens,
+                budget
+            );
+        }
+        if block_count > 0 && preamble_tokens + blocks_tokens + block_tokens > budget {
+            let chunk_text = format!("{}{}\n", preamble, blocks);
+            chunks.push(Chunk {
+                tokens: tokens_of(&chunk_text),
+                text: chunk_text,
+                files: block_count,
+            });
+            blocks.clear();
+            (block_count, blocks_tokens) = (0, 0);
+        }
+        blocks.push_str(block);
+        block_count += 1;
+        blocks_tokens += block_tokens;
+    }
+    // The trailer carries the output's final newline.
+    let chunk_text = format!("{}{}{}", preamble, blocks, &text[trailer_start..]);
+    chunks.push(Chunk {
+        tokens: tokens_of(&chunk_text),
+        text: chunk_text,
+        files: block_count,
+    });
+    chunks
+}
+
+// `out.txt` becomes `out-1.txt`, `out-2.txt`, ... for --split.
+fn chunk_path(output_path: &Path, number: usize) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let file_name = match output_path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, number, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, number),
+    };
+    output_path.with_file_name(file_name)
+}
+
+// Delivers the --split chunks: each to its own numbered file with --output, all of them to
+// stdout in a dry run, and otherwise the first one to the clipboard.
+fn deliver_chunks(
+    chunks: &[Chunk],
+    output_tree_labels_for_console: &[String],
+    cli_args: &cli::Cli,
+) -> Result<()> {
+    macro_rules! info {
+        ($($arg:tt)*) => {
+            if !cli_args.quiet {
+                println!($($arg)*);
+            }
+        };
+    }
+    let files: usize = chunks.iter().map(|chunk| chunk.files).sum();
+    if cli_args.dry_run {
+        for (idx, chunk) in chunks.iter().enumerate() {
             println!(
+                "===== chunk {} of {} ({} files, ≈ {} tokens) =====",
+                idx + 1,
+                chunks.len(),
+                chunk.files,
+                chunk.tokens
+            );
+            print!("{}", chunk.text);
+        }
+        info!(
+            "(Dry run: Would split {} files into {} chunks. Clipboard not affected.)",
+            files,
+            chunks.len()
+        );
+        return Ok(());
+    }
+    if let Some(output_path) = cli_args.output_file.as_ref() {
+        if !output_tree_labels_for_console.is_empty() {
+            for label in output_tree_labels_for_console {
+                info!("{}", label);
+            }
+            info!();
+        }
+        for (idx, chunk) in chunks.iter().enumerate() {
+            let path = chunk_path(output_path, idx + 1);
+            write_file_creating_parents(&path, &chunk.text)?;
+            info!(
                 "✅ Wrote {} files (≈ {} tokens) to {}",
-                files_to_yank_count,
-                tokens,
-                output_path.display()
+                chunk.files,
+                chunk.tokens,
+                path.display()
+            );
+        }
+        return Ok(());
+    }
+    let first = RenderedOutput {
+        text: Some(chunks[0].text.clone()),
+        tokens: chunks[0].tokens,
+        no_files_placeholder: false,
+        file_blocks: Vec::new(),
+        trailer_start: None,
+    };
+    perform_final_action(
+        &first,
+        chunks[0].files,
+        false,
+        output_tree_labels_for_console,
+        cli_args,
+    )?;
+    if chunks.len() > 1 {
+        info!(
+            "ℹ️ The output was split into {} chunks; only chunk 1 was copied. Use --output <FILE> to write them all.",
+            chunks.len()
+        );
+    }
+    Ok(())
+}
+
+// Performs the final action: printing for dry-run or copying to clipboard. Streamed output
+// has already reached stdout or the --output file by now.
+fn perform_final_action(
+    output: &RenderedOutput,
+    files_to_yank_count: usize,
+    initial_scan_was_empty_and_not_default: bool,
+    output_tree_labels_for_console: &[String],
+    cli_args: &cli::Cli,
+) -> Result<()> {
+    // Progress and summary lines, which --quiet suppresses. Errors are always printed.
+    macro_rules! info {
+        ($($arg:tt)*) => {
+            if !cli_args.quiet {
+                println!($($arg)*);
+            }
+        };
+    }
+    let tree_only = cli_args.tree_only;
+    let tokens = output.tokens;
+    // A side artifact the user named, so it is written on dry runs too.
+    if files_to_yank_count > 0
+        && let Some(tree_output_path) = cli_args.tree_output.as_ref()
+    {
+        let mut tree_string = output_tree_labels_for_console.join("\n");
+        tree_string.push('\n');
+        write_file_creating_parents(tree_output_path, &tree_string)?;
+        info!("✅ Wrote tree to {}", tree_output_path.display());
+    }
+    if cli_args.dry_run {
+        // Only buffered when the token budget had to be checked before printing.
+        if let Some(text) = output.text.as_deref() {
+            print!("{}", text);
+        }
+        if files_to_yank_count == 0 {
+            if !output.no_files_placeholder && !initial_scan_was_empty_and_not_default {
+                info!("(Dry run: No files would have been copied based on selection/criteria)");
+            }
+        } else if tree_only {
+            info!(
+                "(Dry run: Would copy tree only, no files copied (≈ {} tokens). Clipboard not affected.)",
+                tokens
             );
         } else {
-            clipboard::copy_text_to_clipboard(output_string.to_string())?;
-            println!(
-                "✅ Copied {} files (≈ {} tokens) to the clipboard.",
+            info!(
+                "(Dry run: Would copy {} files (≈ {} tokens). Clipboard not affected.)",
                 files_to_yank_count, tokens
             );
         }
+    } else if files_to_yank_count == 0 {
+        // This path should only be hit if something went wrong or an edge case led to no files
+        // after initial checks passed.
+        if !output.no_files_placeholder
+            && let Some(text) = output.text.as_deref()
+        {
+            println!("{}", text.trim_end());
+        }
+        println!("No files were ultimately selected to copy. Exiting.");
+        return Err(Exit::NothingToYank.into());
+    } else {
+        // Print the tree structure to console
+        if !output_tree_labels_for_console.is_empty() {
+            for label in output_tree_labels_for_console {
+                info!("{}", label);
+            }
+            info!();
+        }
+
+        if let Some(output_path) = cli_args.output_file.as_ref() {
+            // Buffered only when --send needs the text as well.
+            if let Some(text) = output.text.as_deref() {
+                write_file_creating_parents(output_path, text)?;
+            }
+            if tree_only {
+                info!(
+                    "✅ Wrote tree only, no files copied (≈ {} tokens) to {}",
+                    tokens,
+                    output_path.display()
+                );
+            } else {
+                info!(
+                    "✅ Wrote {} files (≈ {} tokens) to {}",
+                    files_to_yank_count,
+                    tokens,
+                    output_path.display()
+                );
+            }
+        } else {
+            // Refuse oversized payloads up front: some platforms silently truncate or drop
+            // them, which looks like a successful copy followed by an empty paste.
+            let mut text = output.text.as_deref().unwrap_or_default();
+            let appended_text;
+            let mut appended = false;
+            if cli_args.append {
+                match clipboard::read_clipboard_text(cli_args.primary) {
+                    Some(previous) => {
+                        appended_text = clipboard::append_output(&previous, text);
+                        text = &appended_text;
+                        appended = true;
+                    }
+                    None if cli_args.quiet => {}
+                    None => eprintln!(
+                        "ℹ️ The clipboard holds no text to append to; copying the output on its own."
+                    ),
+                }
+            }
+            let max_bytes = cli_args.clipboard_max_bytes;
+            if max_bytes > 0 && text.len() as u64 > max_bytes {
+                eprintln!(
+                    "Error: Output is {} bytes, above the clipboard limit of {} bytes. Not copying.",
+                    text.len(),
+                    max_bytes
+                );
+                eprintln!(
+                    "Use --output <FILE> to write it to a file, narrow the selection, or raise --clipboard-max-bytes (0 disables the limit)."
+                );
+                return Err(Exit::ClipboardFailed.into());
+            }
+            match clipboard::copy_text_to_clipboard(
+                text.to_string(),
+                cli_args.clipboard,
+                cli_args.primary,
+                clipboard::daemon_allowed(cli_args.no_clipboard_daemon),
+            ) {
+                Ok(warnings) => {
+                    for warning in warnings {
+                        eprintln!("⚠️ Warning: {}", warning);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error: Could not copy to the clipboard: {:#}", e);
+                    return Err(Exit::ClipboardFailed.into());
+                }
+            }
+            let verb = if appended { "Appended" } else { "Copied" };
+            if tree_only {
+                info!(
+                    "✅ {} tree only, no files copied (≈ {} tokens) to the clipboard.",
+                    verb, tokens
+                );
+            } else {
+                info!(
+                    "✅ {} {} files (≈ {} tokens) to the clipboard.",
+                    verb, files_to_yank_count, tokens
+                );
+            }
+            if appended {
+                info!(
+                    "   The clipboard now holds ≈ {} tokens in total.",
+                    utils::approx_tokens(text)
+                );
+            }
+        }
     }
     Ok(())
 }
 
+// Drops the files beyond --max-files-per-dir from the selection and, unless --no-warnings,
+// lists them on stderr. Only the paths are needed, so no file is read.
+fn cap_files_per_directory(
+    files_to_yank: &mut Vec<PathBuf>,
+    final_tui_items_for_tree: &mut [tui::SelectableItem],
+    scan_root: &Path,
+    cli_args: &cli::Cli,
+) {
+    let Some(max_per_dir) = cli_args.max_files_per_dir else {
+        return;
+    };
+    let over_cap = files_over_per_directory_cap(
+        files_to_yank,
+        final_tui_items_for_tree,
+        max_per_dir,
+        cli_args.sort,
+    );
+    if over_cap.is_empty() {
+        return;
+    }
+    if !cli_args.no_warnings {
+        eprintln!(
+            "⚠️ Left out {} file(s) beyond --max-files-per-dir {}:",
+            over_cap.len(),
+            max_per_dir
+        );
+        for path in &over_cap {
+            let relative_path = path.strip_prefix(scan_root).unwrap_or(path);
+            eprintln!("    {}", relative_path.display());
+        }
+    }
+    let removed: HashSet<&PathBuf> = over_cap.iter().collect();
+    remove_from_selection(files_to_yank, final_tui_items_for_tree, &removed);
+}
+
+// Reads the files to yank and applies the content options, from --max-files-per-dir and
+// --redact through --max-file-tokens. Files that are left out are dropped from the selection
+// and, unless --no-warnings, listed on stderr. Files are read in parallel, which pays off on
+// slow disks and network mounts; the result keeps the order of `files_to_yank`. Unless
+// `read_ahead`, only the directives at the top of each file are read here, and the output
+// reads the rest.
+fn prepare_file_contents(
+    files_to_yank: &mut Vec<PathBuf>,
+    final_tui_items_for_tree: &mut [tui::SelectableItem],
+    scan_root: &Path,
+    redactor: &redact::Redactor,
+    read_ahead: bool,
+    cli_args: &cli::Cli,
+) -> Result<Vec<YankedFile>> {
+    cap_files_per_directory(files_to_yank, final_tui_items_for_tree, scan_root, cli_args);
+
+    // None of the files are read with --tree-only.
+    if cli_args.tree_only {
+        return Ok(Vec::new());
+    }
+    let prepared: Vec<(Result<YankedFile, Skipped>, usize)> = files_to_yank
+        .par_iter()
+        .map(|file_path| {
+            if !read_ahead {
+                // A repoyank:skip directive is the one thing that still takes a file out of
+                // the tree, and directives only appear in the first lines.
+                let yanked =
+                    if !cli_args.ignore_directives && FileDirectives::read_head(file_path).skip {
+                        Err(Skipped::Directive)
+                    } else {
+                        Ok(YankedFile {
+                            path: file_path.clone(),
+                            body: None,
+                        })
+                    };
+                return (yanked, 0);
+            }
+            let (prepared, redactions) = prepare_file(file_path, redactor, cli_args);
+            let yanked = prepared.map(|body| YankedFile {
+                path: file_path.clone(),
+                body: Some((BodyStats::of(&body), body)),
+            });
+            (yanked, redactions)
+        })
+        .collect();
+
+    let (mut redacted_files, mut total_replacements) = (0, 0);
+    let mut skipped_by_directive = Vec::new();
+    let mut over_token_limit = Vec::new();
+    let mut file_contents = Vec::with_capacity(prepared.len());
+    for ((yanked, redactions), file_path) in prepared.into_iter().zip(files_to_yank.iter()) {
+        if redactions > 0 {
+            redacted_files += 1;
+            total_replacements += redactions;
+        }
+        match yanked {
+            Ok(file) => file_contents.push(file),
+            Err(Skipped::Directive) => skipped_by_directive.push(file_path.clone()),
+            Err(Skipped::OverTokenLimit(tokens)) => {
+                over_token_limit.push((file_path.clone(), tokens))
+            }
+        }
+    }
+    report_redactions(redacted_files, total_replacements, cli_args);
+    if !skipped_by_directive.is_empty() {
+        if !cli_args.no_warnings {
+            eprintln!(
+                "ℹ️ Skipped {} file(s) marked repoyank:skip:",
+                skipped_by_directive.len()
+            );
+            for path in &skipped_by_directive {
+                let relative_path = path.strip_prefix(scan_root).unwrap_or(path);
+                eprintln!("    {}", relative_path.display());
+            }
+        }
+        let skipped_paths: HashSet<&PathBuf> = skipped_by_directive.iter().collect();
+        remove_from_selection(files_to_yank, final_tui_items_for_tree, &skipped_paths);
+    }
+    if let Some(max_tokens) = cli_args.max_file_tokens
+        && !over_token_limit.is_empty()
+    {
+        if !cli_args.no_warnings {
+            eprintln!(
+                "⚠️ Skipped {} file(s) above --max-file-tokens {}:",
+                over_token_limit.len(),
+                max_tokens
+            );
+            for (path, tokens) in &over_token_limit {
+                let relative_path = path.strip_prefix(scan_root).unwrap_or(path);
+                eprintln!("    {} (≈ {} tokens)", relative_path.display(), tokens);
+            }
+        }
+        let skipped_paths: HashSet<&PathBuf> =
+            over_token_limit.iter().map(|(path, _)| path).collect();
+        remove_from_selection(files_to_yank, final_tui_items_for_tree, &skipped_paths);
+    }
+    Ok(file_contents)
+}
+
+// Renders `items` as a confirmed run would and copies the result, for the TUI's `c` key.
+// With --split only the first chunk is copied. Returns the status line message.
+fn copy_selection_to_clipboard(
+    items: &[tui::SelectableItem],
+    scan_root: &Path,
+    cli_args: &cli::Cli,
+    template: Option<&template::Template>,
+) -> Result<String> {
+    let mut items = items.to_vec();
+    let mut files_to_yank: Vec<PathBuf> = items
+        .iter()
+        .filter(|item| !item.is_dir && item.state == tui::SelectionState::FullySelected)
+        .map(|item| item.path.clone())
+        .collect();
+    files_to_yank.sort();
+    if files_to_yank.is_empty() {
+        return Ok("Nothing selected to copy".to_string());
+    }
+    let redactor = redact::Redactor::new(&cli_args.redact, cli_args.redact_secrets)?;
+    let file_contents = prepare_file_contents(
+        &mut files_to_yank,
+        &mut items,
+        scan_root,
+        &redactor,
+        true,
+        cli_args,
+    )?;
+
+    let mut sink = OutputSink::new(OutputTarget::Buffer(String::new()));
+    write_output(
+        &items,
+        &files_to_yank,
+        file_contents,
+        scan_root,
+        &[],
+        template,
+        &redactor,
+        cli_args,
+        &mut sink,
+    )?;
+    let output = sink.finish()?;
+    let text = output.text.as_deref().unwrap_or_default();
+    let (text, files, tokens) = match cli_args.split {
+        Some(budget) => {
+            let first = split_into_chunks(text, &output, budget, true).swap_remove(0);
+            (first.text, first.files, first.tokens)
+        }
+        None => (text.to_string(), files_to_yank.len(), output.tokens),
+    };
+
+    let max_bytes = cli_args.clipboard_max_bytes;
+    if max_bytes > 0 && text.len() as u64 > max_bytes {
+        return Ok(format!(
+            "Not copied: {} bytes is above --clipboard-max-bytes",
+            text.len()
+        ));
+    }
+    // Printing the warnings would corrupt the TUI, so they go on the status line instead.
+    let warnings = clipboard::copy_text_to_clipboard(
+        text,
+        cli_args.clipboard,
+        cli_args.primary,
+        clipboard::daemon_allowed(cli_args.no_clipboard_daemon),
+    )?;
+    let mut message = format!(
+        "Copied {} files (≈ {} tokens)!",
+        files,
+        utils::format_token_count(tokens)
+    );
+    for warning in warnings {
+        message.push_str(&format!(" Warning: {}", warning));
+    }
+    Ok(message)
+}
+
 // Main orchestrator for the repoyank application logic.
-pub fn run_repoyank(cli_args: cli::Cli) -> Result<()> {
+pub fn run_repoyank(mut cli_args: cli::Cli, matches: &clap::ArgMatches) -> Result<()> {
+    // --init only scaffolds a config file; nothing is scanned.
+    if cli_args.init {
+        let path = config::write_starter_config(Path::new("."), cli_args.force)?;
+        println!("Wrote {}. Uncomment the settings you want.", path.display());
+        return Ok(());
+    }
     // Step 1: Determine scan configuration (root directory and glob patterns).
-    let (scan_root, glob_filter_patterns) = determine_scan_configuration(&cli_args)?;
+    let (scan_root, mut glob_filter_patterns) = determine_scan_configuration(&cli_args)?;
+    // The local repoyank.toml lives in the scan root, so defaults are merged once it is known.
+    // PATTERNs themselves are not configurable, so the root cannot change as a result.
+    config::apply_config(&mut cli_args, matches, config::load_config(&scan_root)?);
 
     // Exit if all provided patterns were invalid (and patterns were actually provided, not just default).
     if glob_filter_patterns.is_empty()
         && !cli_args.patterns.is_empty()
-        && !cli_args.patterns.iter().any(|p| p.as_str() == "**/*")
+        && !cli_args
+            .patterns
+            .iter()
+            .any(|p| p.as_str() == DEFAULT_PATTERN)
     {
-        eprintln!("Error: All provided PATTERNs were invalid.");
-        std::process::exit(1);
+        return Err(anyhow!("All provided PATTERNs were invalid."));
     }
+    // Checked before anything is scanned, so a typo never leaves partial output behind.
+    glob_patterns(&cli_args.select_globs, "--select")?;
+    glob_patterns(&cli_args.pin_globs, "--pin")?;
+    // Read up front so a broken template fails before any selection is made.
+    let template = cli_args
+        .template
+        .as_deref()
+        .map(|path| template::Template::load(&config::resolve_template(path, &scan_root)))
+        .transpose()?;
+    // A --from-file list takes the place of the default pattern; PATTERNs given on the
+    // command line still add to it.
+    let listed_files = match &cli_args.from_file {
+        Some(source) => {
+            let list = read_file_list(source, &scan_root)?;
+            glob_filter_patterns.retain(|p| p.as_str() != DEFAULT_PATTERN);
+            glob_filter_patterns.extend(list.patterns);
+            list.files
+        }
+        None => Vec::new(),
+    };
 
     // Step 2: Gather initial candidate files and directories based on patterns and type filters.
+    let scan_options = file_scanner::ScanOptions {
+        include_ignored: cli_args.include_ignored,
+        include_ignored_globs: cli_args.include_ignored_globs.clone(),
+        hidden: cli_args.hidden,
+        follow_symlinks: cli_args.follow_symlinks,
+        max_depth: cli_args.depth,
+        excludes: file_scanner::exclude_patterns(
+            &cli_args.exclude_globs,
+            cli_args.no_default_excludes,
+        )?,
+        progress: !cli_args.quiet,
+    };
+    // --git-modified, --git-diff and --staged restrict the candidates to the files git reports.
+    let git_filter_files = if cli_args.git_modified {
+        let files = git::modified_files(&scan_root)?;
+        if files.is_empty() {
+            println!("No uncommitted changes found to yank.");
+            return Err(Exit::NothingToYank.into());
+        }
+        Some(files)
+    } else if cli_args.staged {
+        let files = git::staged_files(&scan_root)?;
+        if files.is_empty() {
+            println!("No staged files found to yank.");
+            return Err(Exit::NothingToYank.into());
+        }
+        Some(files)
+    } else if let Some(reference) = &cli_args.git_diff {
+        let files = git::files_changed_since(&scan_root, reference)?;
+        if files.is_empty() {
+            println!("No files changed since {}.", reference);
+            return Err(Exit::NothingToYank.into());
+        }
+        Some(files)
+    } else {
+        None
+    };
     let initial_scan_results = gather_initial_candidates(
         &scan_root,
-        &cli_args.type_filter,
-        cli_args.include_ignored,
+        &scan_options,
         &glob_filter_patterns,
+        &listed_files,
+        git_filter_files.as_ref(),
+        &cli_args,
     )?;
 
     // Flag to indicate if the initial scan yielded nothing with specific user-provided criteria.
     let initial_scan_was_empty_and_not_default_pattern = initial_scan_results.is_empty()
-        && !glob_filter_patterns
-            .iter()
-            .any(|p| p.as_str() == "**/*" && cli_args.type_filter.is_empty());
+        && !glob_filter_patterns.iter().any(|p| {
+            p.as_str() == DEFAULT_PATTERN
+                && cli_args.type_filter.is_empty()
+                && cli_args.exclude_types.is_empty()
+        });
 
     // If initial scan is empty with specific criteria, inform user and exit (unless dry-run).
     if initial_scan_was_empty_and_not_default_pattern {
         println!("No files matched the specified patterns and filters.");
         if !cli_args.dry_run {
-            std::process::exit(1);
+            return Err(Exit::NothingToYank.into());
         }
         // For dry run, continue to generate the "(No files...)" output.
     }
 
     // Step 3: Dispatch to headless (--all) mode or interactive TUI mode.
-    let (final_tui_items_for_tree, mut files_to_yank) = if cli_args.all {
+    // --tree-only shows every matched candidate, so there is nothing to pick in the TUI.
+    let (mut final_tui_items_for_tree, mut files_to_yank) = if cli_args.all || cli_args.tree_only {
         // Headless mode.
         let (items, yanks) = run_headless_mode(&initial_scan_results, &scan_root)?;
         if yanks.is_empty() && !cli_args.dry_run && !initial_scan_was_empty_and_not_default_pattern
         {
             println!("No files matched the specified criteria for yanking in --all mode.");
-            std::process::exit(1);
+            return Err(Exit::NothingToYank.into());
         }
         (items, yanks)
     } else {
         // Interactive TUI mode.
-        match run_interactive_mode(&initial_scan_results, &cli_args, &scan_root)? {
-            Some(result) => result, // TUI successful, result contains (items_for_tree, yanks)
-            None => {
-                // TUI was cancelled or had no items to display.
+        match run_interactive_mode(
+            &initial_scan_results,
+            &cli_args,
+            &scan_root,
+            template.as_ref(),
+        )? {
+            InteractiveOutcome::Confirmed(items, yanks) => (items, yanks),
+            InteractiveOutcome::Cancelled => {
+                println!("Selection cancelled. Exiting.");
+                return Err(Exit::Cancelled.into());
+            }
+            InteractiveOutcome::NothingToSelect => {
                 if initial_scan_was_empty_and_not_default_pattern && cli_args.dry_run {
                     // Proceed with empty results for dry run to show "(No files...)" output.
                     (Vec::new(), Vec::new())
-                } else if initial_scan_results.is_empty()
-                    && !cli_args.dry_run
-                    && !initial_scan_was_empty_and_not_default_pattern
-                {
-                    // TUI had no items because initial scan was empty (and not default pattern).
-                    println!("No matching files or directories found to select from in TUI.");
-                    std::process::exit(1);
                 } else {
-                    // TUI cancelled by user, or TUI had no items for other reasons.
-                    println!("Selection cancelled or no items to display. Exiting.");
-                    return Ok(()); // User cancellation is a graceful exit.
+                    println!("No matching files or directories found to select from in TUI.");
+                    return Err(Exit::NothingToYank.into());
                 }
             }
         }
@@ -590,33 +2428,384 @@ pub fn run_repoyank(cli_args: cli::Cli) -> Result<()> {
         && !initial_scan_was_empty_and_not_default_pattern
     {
         println!("No files selected or matched criteria to copy.");
-        std::process::exit(1);
+        return Err(Exit::NothingToYank.into());
     }
 
-    // Step 4: Prepare data for final output string generation.
-    // Get a comprehensive map of all paths under scan_root for accurate is_dir info for the tree.
-    let all_paths_is_dir_map: HashMap<PathBuf, bool> =
-        file_scanner::scan_files(&scan_root, &[], true)?
-            .into_iter()
-            .collect();
+    // --manifest lists what would be yanked instead of producing the output. It only needs the
+    // paths, so no file is read, and the options that judge files by their contents
+    // (repoyank:skip directives, --max-file-tokens) do not apply.
+    if let Some(format) = cli_args.manifest {
+        cap_files_per_directory(
+            &mut files_to_yank,
+            &mut final_tui_items_for_tree,
+            &scan_root,
+            &cli_args,
+        );
+        print_manifest(&files_to_yank, &scan_root, format)?;
+        return Ok(());
+    }
+
+    // Step 4: Prepare data for final output string generation. Unless the files are read
+    // ahead, a streamed output reads each one as it writes it, so they are not all held in
+    // memory at once.
+    let redactor = redact::Redactor::new(&cli_args.redact, cli_args.redact_secrets)?;
+    let file_contents = prepare_file_contents(
+        &mut files_to_yank,
+        &mut final_tui_items_for_tree,
+        &scan_root,
+        &redactor,
+        reads_ahead(&cli_args),
+        &cli_args,
+    )?;
+
+    // --count-only reports what would be yanked instead of producing the output.
+    if cli_args.count_only {
+        print_count_summary(
+            bodies_in_order(file_contents, &redactor, &cli_args),
+            &scan_root,
+            cli_args.no_warnings,
+        );
+        return Ok(());
+    }
+
+    // Candidates that matched the patterns and filters but were not yanked.
+    let omitted_files: Vec<PathBuf> = if cli_args.list_omitted {
+        let yanked: HashSet<&PathBuf> = files_to_yank.iter().collect();
+        initial_scan_results
+            .iter()
+            .filter(|entry| !entry.is_dir && !yanked.contains(&entry.path))
+            .map(|entry| entry.path.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
 
-    // Generate the final output string (tree + file contents).
-    let (console_tree_labels, output_string_for_clipboard) = generate_output_string(
+    // Assemble the output (tree + file contents), streaming it where possible.
+    let mut sink = OutputSink::new(output_target(&cli_args, files_to_yank.len())?);
+    let console_tree_labels = write_output(
         &final_tui_items_for_tree,
         &files_to_yank,
+        file_contents,
         &scan_root,
-        &all_paths_is_dir_map,
+        &omitted_files,
+        template.as_ref(),
+        &redactor,
+        &cli_args,
+        &mut sink,
     )?;
+    let output = sink.finish()?;
+
+    // --split delivers the output in chunks of whole files instead.
+    if let Some(budget) = cli_args.split
+        && !files_to_yank.is_empty()
+        && let Some(text) = output.text.as_deref()
+    {
+        let chunks = split_into_chunks(text, &output, budget, cli_args.no_warnings);
+        return deliver_chunks(&chunks, &console_tree_labels, &cli_args);
+    }
 
     // Step 5: Perform the final action (dry-run print or copy to clipboard).
+    check_token_budget(&output, &cli_args)?;
     perform_final_action(
-        &output_string_for_clipboard,
+        &output,
         files_to_yank.len(),
-        cli_args.dry_run,
         initial_scan_was_empty_and_not_default_pattern,
         &console_tree_labels,
-        &cli_args.output_file,
+        &cli_args,
     )?;
 
+    // Step 6: Optionally hand the output to an LLM API (--send). Never during a dry run.
+    send_output(&output, &cli_args)?;
+
+    Ok(())
+}
+
+// Whether --send needs the output text once it has been delivered.
+#[cfg(feature = "send")]
+fn sends_output(cli_args: &cli::Cli) -> bool {
+    cli_args.send.is_some() && cli_args.prompt.is_some()
+}
+
+#[cfg(not(feature = "send"))]
+fn sends_output(_cli_args: &cli::Cli) -> bool {
+    false
+}
+
+#[cfg(feature = "send")]
+fn send_output(output: &RenderedOutput, cli_args: &cli::Cli) -> Result<()> {
+    if let (Some(provider), Some(prompt)) = (cli_args.send, cli_args.prompt.as_deref()) {
+        if cli_args.dry_run {
+            println!("(Dry run: --send skipped, no network request made.)");
+        } else {
+            println!("📨 Sending to the {} API...\n", provider.display_name());
+            crate::send::send_and_stream(
+                provider,
+                prompt,
+                output.text.as_deref().unwrap_or_default(),
+            )?;
+        }
+    }
     Ok(())
 }
+
+#[cfg(not(feature = "send"))]
+fn send_output(_output: &RenderedOutput, _cli_args: &cli::Cli) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    // Creates `files` (with parent directories) under a fresh temporary directory.
+    fn tree(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (file, contents) in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn cli(args: &[&str]) -> cli::Cli {
+        cli::Cli::try_parse_from(std::iter::once("repoyank").chain(args.iter().copied())).unwrap()
+    }
+
+    // The candidates for `cli_args` below `root`, with the default pattern.
+    fn candidates(root: &Path, cli_args: &cli::Cli) -> Vec<file_scanner::ScannedEntry> {
+        let scan_options = file_scanner::ScanOptions {
+            follow_symlinks: cli_args.follow_symlinks,
+            ..file_scanner::ScanOptions::default()
+        };
+        let patterns = vec![Pattern::new(DEFAULT_PATTERN).unwrap()];
+        gather_initial_candidates(root, &scan_options, &patterns, &[], None, cli_args).unwrap()
+    }
+
+    // The candidate files for `args` (options only, no PATTERNs) below `root`, relative to it.
+    fn candidate_files(root: &Path, args: &[&str]) -> Vec<String> {
+        candidates(root, &cli(args))
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| {
+                let relative = entry.path.strip_prefix(root).unwrap();
+                relative.to_string_lossy().into_owned()
+            })
+            .collect()
+    }
+
+    // The buffered output for the selection in `items` and `files_to_yank`.
+    fn render(
+        root: &Path,
+        cli_args: &cli::Cli,
+        mut items: Vec<tui::SelectableItem>,
+        mut files_to_yank: Vec<PathBuf>,
+    ) -> String {
+        let redactor = redact::Redactor::new(&cli_args.redact, cli_args.redact_secrets).unwrap();
+        let file_contents = prepare_file_contents(
+            &mut files_to_yank,
+            &mut items,
+            root,
+            &redactor,
+            reads_ahead(cli_args),
+            cli_args,
+        )
+        .unwrap();
+        let mut sink = OutputSink::new(OutputTarget::Buffer(String::new()));
+        write_output(
+            &items,
+            &files_to_yank,
+            file_contents,
+            root,
+            &[],
+            None,
+            &redactor,
+            cli_args,
+            &mut sink,
+        )
+        .unwrap();
+        sink.finish().unwrap().text.unwrap()
+    }
+
+    #[test]
+    fn directories_without_a_yanked_file_are_pruned_from_the_tree() {
+        let dir = tree(&[
+            ("kept/a.rs", "fn a() {}"),
+            ("skipped/only.rs", "// repoyank:skip\n"),
+        ]);
+        fs::create_dir(dir.path().join("empty")).unwrap();
+        let tree_of = |args: &[&str]| {
+            let cli_args = cli(args);
+            let (mut items, files) =
+                run_headless_mode(&candidates(dir.path(), &cli_args), dir.path()).unwrap();
+            // As if the empty directory had been selected in the TUI.
+            items.push(tui::SelectableItem {
+                path: dir.path().join("empty"),
+                display_text: String::new(),
+                is_dir: true,
+                is_expanded: true,
+                state: tui::SelectionState::FullySelected,
+                children_indices: Vec::new(),
+                parent_index: None,
+                modified: None,
+                size: 0,
+            });
+            let output = render(dir.path(), &cli_args, items, files);
+            output.split("\n\n").next().unwrap().to_string()
+        };
+        assert_eq!(tree_of(&["--no-warnings"]), "./\n└─ kept/\n   └─ a.rs");
+        assert_eq!(
+            tree_of(&["--no-warnings", "--include-empty-dirs"]),
+            "./\n├─ empty/\n├─ kept/\n│  └─ a.rs\n└─ skipped/"
+        );
+    }
+
+    #[test]
+    fn a_root_with_a_type_and_no_pattern_yanks_every_file_of_that_type() {
+        let dir = tree(&[
+            ("src/a.rs", "x"),
+            ("src/b.txt", "x"),
+            ("src/sub/c.rs", "x"),
+            ("other.rs", "x"),
+        ]);
+        let src = dir.path().join("src");
+        // `repoyank -t rs src`
+        let cli_args = cli(&["-t", "rs", src.to_str().unwrap()]);
+        let (scan_root, patterns) = determine_scan_configuration(&cli_args).unwrap();
+        assert_eq!(scan_root, src.canonicalize().unwrap());
+        let pattern_strs: Vec<&str> = patterns.iter().map(Pattern::as_str).collect();
+        assert_eq!(pattern_strs, vec![DEFAULT_PATTERN]);
+
+        let scan_options = file_scanner::ScanOptions::default();
+        let files: Vec<PathBuf> =
+            gather_initial_candidates(&scan_root, &scan_options, &patterns, &[], None, &cli_args)
+                .unwrap()
+                .into_iter()
+                .filter(|entry| !entry.is_dir)
+                .map(|entry| entry.path.strip_prefix(&scan_root).unwrap().to_path_buf())
+                .collect();
+        assert_eq!(files, vec![Path::new("a.rs"), Path::new("sub/c.rs")]);
+    }
+
+    #[test]
+    fn label_language_falls_back_to_unknown() {
+        let dir = tree(&[("main.rs", "fn main() {}"), ("notes.weird", "hi")]);
+        let cli_args = cli(&["--label-language"]);
+        let (items, files) =
+            run_headless_mode(&candidates(dir.path(), &cli_args), dir.path()).unwrap();
+        let output = render(dir.path(), &cli_args, items, files);
+        assert!(
+            output.contains("File: main.rs\nLanguage: Rust\n"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("File: notes.weird\nLanguage: Unknown\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn newer_than_picks_files_modified_within_the_window() {
+        let dir = tree(&[("old.txt", "x"), ("recent.txt", "x"), ("future.txt", "x")]);
+        let now = std::time::SystemTime::now();
+        let hours = |n: u64| std::time::Duration::from_secs(n * 3600);
+        for (file, modified) in [
+            ("old.txt", now - hours(48)),
+            ("recent.txt", now - hours(1)),
+            ("future.txt", now + hours(1)),
+        ] {
+            let file = fs::File::options()
+                .write(true)
+                .open(dir.path().join(file))
+                .unwrap();
+            file.set_modified(modified).unwrap();
+        }
+        let (items, _) = run_headless_mode(&candidates(dir.path(), &cli(&[])), dir.path()).unwrap();
+        let picked = |window| -> Vec<&Path> {
+            modified_within(&items, window, now)
+                .into_iter()
+                .map(|idx| items[idx].path.strip_prefix(dir.path()).unwrap())
+                .collect()
+        };
+        assert_eq!(
+            picked(hours(24)),
+            vec![Path::new("future.txt"), Path::new("recent.txt")]
+        );
+        assert_eq!(
+            picked(hours(72)),
+            vec![
+                Path::new("future.txt"),
+                Path::new("old.txt"),
+                Path::new("recent.txt")
+            ]
+        );
+    }
+
+    #[test]
+    fn header_paths_follow_the_path_style() {
+        let path = Path::new("repo/src/main.rs");
+        let absolute = std::env::current_dir().unwrap().join(path);
+        let cases = [
+            (cli::PathStyle::Relative, None, PathBuf::from("src/main.rs")),
+            // --paths-from-cwd, run from inside src/.
+            (
+                cli::PathStyle::Relative,
+                Some(Path::new("repo/src")),
+                PathBuf::from("main.rs"),
+            ),
+            (cli::PathStyle::Absolute, None, absolute.clone()),
+            (
+                cli::PathStyle::Absolute,
+                Some(Path::new("repo/src")),
+                absolute,
+            ),
+            (cli::PathStyle::Name, None, PathBuf::from("main.rs")),
+        ];
+        for (style, cwd, expected) in cases {
+            assert_eq!(
+                header_path(path, Path::new("repo"), cwd, style),
+                expected.as_path(),
+                "{:?} with cwd {:?}",
+                style,
+                cwd
+            );
+        }
+    }
+
+    #[test]
+    fn min_file_size_keeps_files_at_or_above_the_threshold() {
+        let dir = tree(&[
+            ("empty.txt", ""),
+            ("three.txt", "abc"),
+            ("four.txt", "abcd"),
+            ("five.txt", "abcde"),
+        ]);
+        assert_eq!(
+            candidate_files(dir.path(), &["--min-file-size", "4"]),
+            vec!["five.txt", "four.txt"]
+        );
+        // Empty files go whenever the option is set, even at 0.
+        assert_eq!(
+            candidate_files(dir.path(), &["--min-file-size", "0"]),
+            vec!["five.txt", "four.txt", "three.txt"]
+        );
+        assert_eq!(candidate_files(dir.path(), &[]).len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_file_reached_through_symlinks_is_yielded_once() {
+        let dir = tree(&[("a/real.txt", "x")]);
+        std::os::unix::fs::symlink("a/real.txt", dir.path().join("link.txt")).unwrap();
+        std::os::unix::fs::symlink("a", dir.path().join("alias")).unwrap();
+        assert_eq!(candidate_files(dir.path(), &[]), vec!["a/real.txt"]);
+        // Followed, the directory link leads back to the same file a second way.
+        assert_eq!(
+            candidate_files(dir.path(), &["--follow-symlinks"]),
+            vec!["a/real.txt"]
+        );
+    }
+}